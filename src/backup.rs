@@ -0,0 +1,153 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Project-local state of andrust, kept out of version control.
+pub const STATE_DIR: &str = ".andrust";
+const BACKUP_DIR: &str = "backups";
+
+/// Cargo configs saved before andrust rewrote them, one directory per backup.
+///
+/// ```text
+/// <project>/.andrust/backups/<id>/config.toml
+/// ```
+///
+/// The id is the UTC time of the backup, e.g. `20261018-153012`.
+pub struct BackupStore {
+    root: PathBuf,
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Backup {
+    id: String,
+    path: PathBuf,
+}
+
+impl BackupStore {
+    pub fn new(proj_root: &Path) -> Self {
+        BackupStore {
+            root: proj_root.join(STATE_DIR).join(BACKUP_DIR),
+        }
+    }
+
+    /// Copies the cargo config at `config_path`, if it exists, into a new backup.
+    pub fn backup(&self, config_path: &Path) -> io::Result<Option<Backup>> {
+        let file_name = match (config_path.is_file(), config_path.file_name()) {
+            (true, Some(file_name)) => file_name,
+            _ => return Ok(None),
+        };
+
+        fs::create_dir_all(&self.root)?;
+        if let Some(state_dir) = self.root.parent() {
+            let gitignore = state_dir.join(".gitignore");
+            if !gitignore.exists() {
+                fs::write(gitignore, "*\n")?;
+            }
+        }
+
+        let timestamp = format_timestamp(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or(0),
+        );
+        let mut id = timestamp.clone();
+        let mut count = 1;
+        while self.root.join(id.as_str()).exists() {
+            count += 1;
+            id = format!("{}-{}", timestamp, count);
+        }
+
+        let backup_dir = self.root.join(id.as_str());
+        fs::create_dir_all(&backup_dir)?;
+        let path = backup_dir.join(file_name);
+        fs::copy(config_path, &path)?;
+        Ok(Some(Backup { id, path }))
+    }
+
+    /// Every backup, the oldest first.
+    pub fn list(&self) -> Vec<Backup> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+
+        let mut backups: Vec<Backup> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let id = entry.file_name().to_str()?.to_owned();
+                let path = fs::read_dir(entry.path())
+                    .ok()?
+                    .filter_map(|file| file.ok())
+                    .map(|file| file.path())
+                    .find(|file| file.is_file())?;
+                Some(Backup { id, path })
+            })
+            .collect();
+        backups.sort_by(|lhs, rhs| {
+            (lhs.id.len(), lhs.id.as_str()).cmp(&(rhs.id.len(), rhs.id.as_str()))
+        });
+        backups
+    }
+
+    pub fn get(&self, id: &str) -> Option<Backup> {
+        self.list().into_iter().find(|backup| backup.id == id)
+    }
+
+    pub fn latest(&self) -> Option<Backup> {
+        self.list().pop()
+    }
+}
+
+impl Backup {
+    pub fn id(&self) -> &str {
+        self.id.as_str()
+    }
+
+    /// The saved cargo config, named like the original one.
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+
+    /// Copies the saved cargo config back into `<proj_root>/.cargo`.
+    pub fn restore(&self, proj_root: &Path) -> io::Result<PathBuf> {
+        let file_name = self
+            .path
+            .file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no file name"))?;
+        let cargo_dir = proj_root.join(".cargo");
+        fs::create_dir_all(&cargo_dir)?;
+        let config_path = cargo_dir.join(file_name);
+        fs::copy(&self.path, &config_path)?;
+        Ok(config_path)
+    }
+}
+
+/// Formats seconds since the unix epoch as `YYYYMMDD-HHMMSS` in UTC.
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let secs_of_day = secs % 86400;
+
+    // Converts days to a civil date, from Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
@@ -0,0 +1,77 @@
+use super::{
+    backup::{format_timestamp, BackupStore},
+    platform::ConfigWriter,
+};
+
+use std::fs;
+
+#[test]
+fn format_timestamp_test() {
+    assert_eq!(format_timestamp(0), "19700101-000000");
+    assert_eq!(format_timestamp(951_782_400), "20000229-000000");
+    assert_eq!(format_timestamp(1_792_337_412), "20261018-153012");
+}
+
+#[test]
+fn backup_restore_test() {
+    let proj_root = std::env::temp_dir().join("andrust_backup_test");
+    let _ = fs::remove_dir_all(&proj_root);
+    let config_path = proj_root.join(".cargo/config.toml");
+    let store = BackupStore::new(proj_root.as_path());
+
+    assert_eq!(store.backup(config_path.as_path()).unwrap(), None);
+    assert!(store.list().is_empty());
+
+    fs::create_dir_all(config_path.parent().unwrap()).unwrap();
+    fs::write(&config_path, "[build]\njobs = 1\n").unwrap();
+    let first = store.backup(config_path.as_path()).unwrap().unwrap();
+    fs::write(&config_path, "[build]\njobs = 2\n").unwrap();
+    let second = store.backup(config_path.as_path()).unwrap().unwrap();
+
+    assert_ne!(first.id(), second.id());
+    assert_eq!(store.list(), vec![first, second]);
+    assert!(proj_root.join(".andrust/.gitignore").exists());
+
+    fs::write(&config_path, "").unwrap();
+    let restored = store
+        .get(store.list()[0].id())
+        .unwrap()
+        .restore(proj_root.as_path())
+        .unwrap();
+    assert_eq!(restored, config_path);
+    assert_eq!(
+        fs::read_to_string(&config_path).unwrap(),
+        "[build]\njobs = 1\n"
+    );
+    assert_eq!(
+        fs::read_to_string(store.latest().unwrap().path()).unwrap(),
+        "[build]\njobs = 2\n"
+    );
+    let _ = fs::remove_dir_all(&proj_root);
+}
+
+#[test]
+fn unconfigure_test() {
+    let existing = r#"[build]
+jobs = 2
+
+[target.aarch64-linux-android]
+linker = "my-clang"
+
+# managed by andrust
+[target.x86_64-linux-android]
+ar = "llvm-ar"
+linker = "x86_64-linux-android21-clang"
+"#;
+    let (content, removed) = ConfigWriter::remove_managed(existing).unwrap();
+    assert_eq!(removed, vec!["x86_64-linux-android".to_owned()]);
+    assert!(content.contains("[target.aarch64-linux-android]"));
+    assert!(!content.contains("x86_64"));
+
+    let (content, removed) = ConfigWriter::remove_managed(
+        "[build]\njobs = 2\n\n# managed by andrust\n[target.x86_64-linux-android]\nar = \"llvm-ar\"\n",
+    )
+    .unwrap();
+    assert_eq!(removed.len(), 1);
+    assert_eq!(content, "[build]\njobs = 2\n");
+}
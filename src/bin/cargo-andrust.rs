@@ -0,0 +1,8 @@
+//! `cargo andrust`, installed next to `andrust` by `cargo install`.
+
+#[path = "../main.rs"]
+mod andrust;
+
+fn main() {
+    andrust::main()
+}
@@ -0,0 +1,282 @@
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    io::{self, BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::platform::TargetSpec;
+
+/// Runs `cargo build --target <triple>` in the project for each target.
+///
+/// The output of cargo is streamed with the triple as a prefix, and `jobs` targets are
+/// built at once. Every target is built even when another one fails, and the results
+/// are reported in order of the targets.
+pub struct Builder {
+    cargo: OsString,
+    proj_root: PathBuf,
+    manifest_path: Option<PathBuf>,
+    target_dir: PathBuf,
+    jni_libs: Option<PathBuf>,
+    release: bool,
+    jobs: usize,
+    cargo_args: Vec<String>,
+}
+
+#[derive(PartialEq, Debug)]
+pub enum BuildOutcome {
+    /// Built into the output directory.
+    Built(PathBuf),
+    /// cargo exited with the code, which is `None` when it is killed by a signal.
+    Failed(Option<i32>),
+    /// cargo or the shared libraries could not be handled.
+    Error(String),
+}
+
+#[derive(Debug)]
+pub struct BuildReport {
+    triple: String,
+    outcome: BuildOutcome,
+    elapsed: Duration,
+}
+
+impl Builder {
+    /// Runs `$CARGO`, which cargo sets for its subcommands, or `cargo` on `PATH`.
+    pub fn new(proj_root: &Path, target_dir: &Path) -> Self {
+        Builder {
+            cargo: env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo")),
+            proj_root: proj_root.to_path_buf(),
+            manifest_path: None,
+            target_dir: target_dir.to_path_buf(),
+            jni_libs: None,
+            release: false,
+            jobs: 1,
+            cargo_args: Vec::new(),
+        }
+    }
+
+    pub fn manifest_path(mut self, manifest_path: Option<&Path>) -> Self {
+        self.manifest_path = manifest_path.map(Path::to_path_buf);
+        self
+    }
+
+    /// Copies the `lib*.so` files of each target into `<jni_libs>/<abi>`.
+    pub fn jni_libs(mut self, jni_libs: Option<&Path>) -> Self {
+        self.jni_libs = jni_libs.map(Path::to_path_buf);
+        self
+    }
+
+    pub fn release(mut self, release: bool) -> Self {
+        self.release = release;
+        self
+    }
+
+    pub fn jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Arguments passed to every `cargo build` after the ones of andrust.
+    pub fn cargo_args(mut self, cargo_args: &[String]) -> Self {
+        self.cargo_args = cargo_args.to_vec();
+        self
+    }
+
+    pub fn build(&self, triples: &[&str]) -> Vec<BuildReport> {
+        let next = Mutex::new(0);
+        let reports: Mutex<Vec<Option<BuildReport>>> =
+            Mutex::new(triples.iter().map(|_| None).collect());
+
+        thread::scope(|scope| {
+            for _ in 0..self.jobs.min(triples.len()) {
+                scope.spawn(|| loop {
+                    let index = {
+                        let mut next = next.lock().unwrap_or_else(|err| err.into_inner());
+                        *next += 1;
+                        *next - 1
+                    };
+                    let triple = match triples.get(index) {
+                        Some(triple) => *triple,
+                        None => break,
+                    };
+                    let report = self.build_target(triple);
+                    reports.lock().unwrap_or_else(|err| err.into_inner())[index] = Some(report);
+                });
+            }
+        });
+
+        reports
+            .into_inner()
+            .unwrap_or_else(|err| err.into_inner())
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    fn build_target(&self, triple: &str) -> BuildReport {
+        let started_at = Instant::now();
+        let outcome = match self.run_cargo(triple) {
+            Ok(status) if status.success() => {
+                let profile = if self.release { "release" } else { "debug" };
+                let output_dir = self.target_dir.join(triple).join(profile);
+                match self.copy_jni_libs(triple, output_dir.as_path()) {
+                    Ok(()) => BuildOutcome::Built(output_dir),
+                    Err(err) => {
+                        BuildOutcome::Error(format!("failed to copy the libraries : {}", err))
+                    }
+                }
+            }
+            Ok(status) => BuildOutcome::Failed(status.code()),
+            Err(err) => BuildOutcome::Error(format!("failed to run cargo : {}", err)),
+        };
+
+        BuildReport {
+            triple: triple.to_owned(),
+            outcome,
+            elapsed: started_at.elapsed(),
+        }
+    }
+
+    fn run_cargo(&self, triple: &str) -> io::Result<ExitStatus> {
+        let mut command = Command::new(&self.cargo);
+        command
+            .args(["build", "--target", triple])
+            .arg("--target-dir")
+            .arg(&self.target_dir)
+            .current_dir(&self.proj_root)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if self.release {
+            command.arg("--release");
+        }
+        if let Some(manifest_path) = self.manifest_path.as_ref() {
+            command.arg("--manifest-path").arg(manifest_path);
+        }
+        command.args(&self.cargo_args);
+
+        println!("[{}] building", triple);
+        let mut child = command.spawn()?;
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        thread::scope(|scope| {
+            if let Some(stdout) = stdout {
+                scope.spawn(|| stream_lines(triple, stdout, false));
+            }
+            if let Some(stderr) = stderr {
+                scope.spawn(|| stream_lines(triple, stderr, true));
+            }
+        });
+        child.wait()
+    }
+
+    fn copy_jni_libs(&self, triple: &str, output_dir: &Path) -> io::Result<()> {
+        let (jni_libs, spec) = match (self.jni_libs.as_ref(), TargetSpec::find(triple)) {
+            (Some(jni_libs), Some(spec)) => (jni_libs, spec),
+            _ => return Ok(()),
+        };
+        let abi_dir = jni_libs.join(spec.abi);
+        for library in copy_libraries(output_dir, abi_dir.as_path())? {
+            println!(
+                "[{}] {} is copied to {}",
+                triple,
+                library,
+                abi_dir.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+impl BuildReport {
+    pub fn is_built(&self) -> bool {
+        matches!(self.outcome, BuildOutcome::Built(_))
+    }
+}
+
+#[cfg(test)]
+impl BuildReport {
+    pub fn triple(&self) -> &str {
+        self.triple.as_str()
+    }
+
+    pub fn outcome(&self) -> &BuildOutcome {
+        &self.outcome
+    }
+}
+
+/// Prints each line of `output` with the triple as a prefix.
+fn stream_lines<R: Read>(triple: &str, output: R, is_stderr: bool) {
+    for line in BufReader::new(output).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        if is_stderr {
+            eprintln!("[{}] {}", triple, line);
+        } else {
+            println!("[{}] {}", triple, line);
+        }
+    }
+}
+
+/// A table of the result and the build time of each target.
+pub fn format_summary(reports: &[BuildReport]) -> String {
+    let width = reports
+        .iter()
+        .map(|report| report.triple.len())
+        .max()
+        .unwrap_or(0)
+        .max("target".len());
+
+    let mut summary = format!(
+        "{:<width$}  {:<6}  {:>7}  output\n",
+        "target",
+        "result",
+        "time",
+        width = width
+    );
+    for report in reports {
+        let (result, detail) = match &report.outcome {
+            BuildOutcome::Built(output_dir) => ("ok", output_dir.display().to_string()),
+            BuildOutcome::Failed(Some(code)) => ("failed", format!("cargo exited with {}", code)),
+            BuildOutcome::Failed(None) => ("failed", "cargo is killed".to_owned()),
+            BuildOutcome::Error(reason) => ("error", reason.to_owned()),
+        };
+        summary.push_str(
+            format!(
+                "{:<width$}  {:<6}  {:>6.1}s  {}\n",
+                report.triple,
+                result,
+                report.elapsed.as_secs_f64(),
+                detail,
+                width = width
+            )
+            .as_str(),
+        );
+    }
+    summary
+}
+
+/// Copies the `lib*.so` files in `output_dir` into `abi_dir`, and returns their names.
+pub fn copy_libraries(output_dir: &Path, abi_dir: &Path) -> io::Result<Vec<String>> {
+    let mut libraries: Vec<String> = fs::read_dir(output_dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.starts_with("lib") && name.ends_with(".so"))
+        .collect();
+    libraries.sort();
+
+    if !libraries.is_empty() {
+        fs::create_dir_all(abi_dir)?;
+    }
+    for library in &libraries {
+        fs::copy(output_dir.join(library), abi_dir.join(library))?;
+    }
+    Ok(libraries)
+}
@@ -0,0 +1,153 @@
+use super::{
+    builder::{self, BuildOutcome, Builder},
+    command::{Command, CommandState, NdkCommand},
+};
+
+use std::{env, fs, path::Path};
+
+/// Set by `build_test` for the child process which runs `fake_cargo_build`.
+const FAKE_CARGO_ROOT: &str = "ANDRUST_FAKE_CARGO_ROOT";
+
+/// A cargo which fails for armv7, and leaves a library for the other targets.
+#[cfg(unix)]
+fn create_fake_cargo(dir: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::create_dir_all(dir).unwrap();
+    let cargo = dir.join("cargo");
+    fs::write(
+        &cargo,
+        r#"#!/bin/sh
+echo "args $*"
+triple=$3
+target_dir=$5
+case "$*" in *--release*) profile=release ;; *) profile=debug ;; esac
+if [ "$triple" = "armv7-linux-androideabi" ]; then
+    echo "error: linker not found" >&2
+    exit 101
+fi
+mkdir -p "$target_dir/$triple/$profile"
+touch "$target_dir/$triple/$profile/libfake.so"
+"#,
+    )
+    .unwrap();
+    fs::set_permissions(&cargo, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+/// Runs `fake_cargo_build` in a child process with the fake cargo first on `PATH`,
+/// so that the other tests still run the real cargo.
+#[cfg(unix)]
+#[test]
+fn build_test() {
+    let proj_root = env::temp_dir().join("andrust_builder_test");
+    let _ = fs::remove_dir_all(&proj_root);
+    let bin = proj_root.join("bin");
+    create_fake_cargo(bin.as_path());
+    let path = env::var_os("PATH").unwrap_or_default();
+    let path = env::join_paths(std::iter::once(bin).chain(env::split_paths(&path))).unwrap();
+
+    let output = std::process::Command::new(env::current_exe().unwrap())
+        .args([
+            "builder_test::fake_cargo_build",
+            "--exact",
+            "--ignored",
+            "--test-threads=1",
+        ])
+        .env("PATH", path)
+        .env_remove("CARGO")
+        .env(FAKE_CARGO_ROOT, proj_root.as_os_str())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("1 passed"), "{}", stdout);
+}
+
+#[test]
+#[ignore = "run by build_test with a fake cargo on PATH"]
+fn fake_cargo_build() {
+    let proj_root = match env::var_os(FAKE_CARGO_ROOT) {
+        Some(proj_root) => std::path::PathBuf::from(proj_root),
+        None => return,
+    };
+    let target_dir = proj_root.join("target");
+    let jni_libs = proj_root.join("jniLibs");
+
+    let reports = Builder::new(proj_root.as_path(), target_dir.as_path())
+        .jni_libs(Some(jni_libs.as_path()))
+        .release(true)
+        .jobs(2)
+        .cargo_args(&["--features".to_owned(), "jni".to_owned()])
+        .build(&[
+            "aarch64-linux-android",
+            "armv7-linux-androideabi",
+            "x86_64-linux-android",
+        ]);
+
+    let triples: Vec<&str> = reports.iter().map(|report| report.triple()).collect();
+    assert_eq!(
+        triples,
+        vec![
+            "aarch64-linux-android",
+            "armv7-linux-androideabi",
+            "x86_64-linux-android"
+        ]
+    );
+    assert_eq!(
+        reports[0].outcome(),
+        &BuildOutcome::Built(target_dir.join("aarch64-linux-android/release"))
+    );
+    assert_eq!(reports[1].outcome(), &BuildOutcome::Failed(Some(101)));
+    assert!(reports[2].is_built());
+    assert!(jni_libs.join("arm64-v8a/libfake.so").is_file());
+    assert!(jni_libs.join("x86_64/libfake.so").is_file());
+    assert!(!jni_libs.join("armeabi-v7a").exists());
+
+    let summary = builder::format_summary(&reports);
+    let lines: Vec<&str> = summary.lines().collect();
+    assert!(lines[0].starts_with("target "));
+    assert!(lines[2].starts_with("armv7-linux-androideabi  failed"));
+    assert!(lines[2].ends_with("cargo exited with 101"));
+
+    // This process runs only this test, so it can change its own PATH.
+    env::set_var("PATH", proj_root.join("no-cargo"));
+    let missing =
+        Builder::new(proj_root.as_path(), target_dir.as_path()).build(&["aarch64-linux-android"]);
+    assert!(matches!(missing[0].outcome(), BuildOutcome::Error(_)));
+}
+
+#[test]
+fn build_options_test() {
+    let cmd_opts = match CommandState::from_args(vec![
+        "build",
+        "--release",
+        "-j",
+        "3",
+        "--",
+        "--features",
+        "jni",
+    ]) {
+        CommandState::Options(cmd_opts) => cmd_opts,
+        _ => panic!("invalid build options"),
+    };
+    assert!(cmd_opts.release());
+    assert_eq!(cmd_opts.jobs(), 3);
+    assert_eq!(cmd_opts.cargo_args(), &["--features", "jni"]);
+
+    assert!(matches!(
+        CommandState::from_args(vec!["build", "--jobs", "0"]),
+        CommandState::Invalid(_)
+    ));
+    assert!(matches!(
+        CommandState::from_args(vec!["setup", "--release"]),
+        CommandState::Invalid(_)
+    ));
+    // Only build passes the arguments after -- to cargo.
+    match CommandState::from_args(vec!["ndk", "--", "remove", "r25c"]) {
+        CommandState::Options(cmd_opts) => {
+            assert!(cmd_opts.cargo_args().is_empty());
+            assert!(cmd_opts.command() == &Command::Ndk(NdkCommand::Remove("r25c".to_owned())));
+        }
+        _ => panic!("-- ends the options"),
+    }
+}
@@ -0,0 +1,160 @@
+use std::{collections::HashMap, env, fs, path::PathBuf};
+
+use serde::Deserialize;
+
+use super::ndk::{NdkRevision, NdkVersionReq};
+
+const BUNDLED_CATALOG: &str = include_str!("../source_config/download.json");
+
+#[derive(Deserialize)]
+pub struct ReleaseCatalog {
+    default: Option<String>,
+    #[serde(default)]
+    releases: Vec<NdkRelease>,
+}
+
+#[derive(Deserialize)]
+pub struct NdkRelease {
+    version: String,
+    revision: Option<String>,
+    #[serde(default)]
+    hosts: HashMap<String, HostPackage>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct HostPackage {
+    urls: Vec<String>,
+    size: Option<u64>,
+    sha1: Option<String>,
+    sha256: Option<String>,
+}
+
+impl ReleaseCatalog {
+    pub fn bundled() -> Self {
+        serde_json::from_str(BUNDLED_CATALOG).expect("bundled download.json is malformed")
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Loads the bundled catalog and overlays the user catalog on it.
+    ///
+    /// The user catalog is read from `ANDRUST_DOWNLOAD_CONFIG` when it is set,
+    /// otherwise from `~/.andrust/download.json`.
+    pub fn load() -> Self {
+        let mut catalog = ReleaseCatalog::bundled();
+
+        let user_catalog = ReleaseCatalog::user_catalog_path()
+            .filter(|path| path.exists())
+            .and_then(|path| match fs::read_to_string(&path) {
+                Ok(json) => match ReleaseCatalog::from_json(json.as_str()) {
+                    Ok(user_catalog) => Some(user_catalog),
+                    Err(err) => {
+                        println!("{} is ignored : {}", path.display(), err);
+                        None
+                    }
+                },
+                Err(_) => None,
+            });
+        if let Some(user_catalog) = user_catalog {
+            catalog.merge(user_catalog);
+        }
+
+        catalog
+    }
+
+    fn user_catalog_path() -> Option<PathBuf> {
+        env::var("ANDRUST_DOWNLOAD_CONFIG")
+            .map(PathBuf::from)
+            .or_else(|_| {
+                env::var("HOME")
+                    .or_else(|_| env::var("USERPROFILE"))
+                    .map(|home_path| PathBuf::from(home_path).join(".andrust/download.json"))
+            })
+            .ok()
+    }
+
+    /// Releases of `other` replace the releases of the same version.
+    pub fn merge(&mut self, other: ReleaseCatalog) {
+        if other.default.is_some() {
+            self.default = other.default;
+        }
+
+        for release in other.releases {
+            match self
+                .releases
+                .iter_mut()
+                .find(|known| known.version == release.version)
+            {
+                Some(known) => *known = release,
+                None => self.releases.push(release),
+            }
+        }
+    }
+
+    pub fn default_version(&self) -> Option<&str> {
+        self.default
+            .as_deref()
+            .or_else(|| self.releases.last().map(|release| release.version()))
+    }
+
+    pub fn release(&self, version: &str) -> Option<&NdkRelease> {
+        self.releases.iter().find(|release| {
+            release.version == version || release.revision.as_deref() == Some(version)
+        })
+    }
+
+    /// The newest release for this host which matches `requirement`.
+    pub fn newest_release(&self, requirement: &NdkVersionReq) -> Option<&NdkRelease> {
+        self.releases
+            .iter()
+            .filter(|release| release.host_package().is_some())
+            .filter_map(|release| release.ndk_revision().map(|revision| (revision, release)))
+            .filter(|(revision, _)| requirement.matches(revision))
+            .max_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs))
+            .map(|(_, release)| release)
+    }
+}
+
+impl NdkRelease {
+    pub fn version(&self) -> &str {
+        self.version.as_str()
+    }
+
+    pub fn revision(&self) -> Option<&str> {
+        self.revision.as_deref()
+    }
+
+    pub fn ndk_revision(&self) -> Option<NdkRevision> {
+        self.revision()
+            .and_then(NdkRevision::parse)
+            .or_else(|| NdkRevision::parse(self.version()))
+    }
+
+    pub fn package(&self, host: &str) -> Option<&HostPackage> {
+        self.hosts.get(host)
+    }
+
+    pub fn host_package(&self) -> Option<&HostPackage> {
+        self.package(env::consts::OS)
+    }
+}
+
+impl HostPackage {
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    pub fn sha1(&self) -> Option<&str> {
+        self.sha1.as_deref()
+    }
+
+    pub fn sha256(&self) -> Option<&str> {
+        self.sha256.as_deref()
+    }
+}
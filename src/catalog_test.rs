@@ -0,0 +1,50 @@
+use super::catalog::ReleaseCatalog;
+
+#[test]
+fn bundled_catalog_test() {
+    let catalog = ReleaseCatalog::bundled();
+    let version = catalog.default_version().unwrap();
+    let release = catalog.release(version).unwrap();
+
+    for host in &["linux", "macos", "windows"] {
+        let package = release.package(host).unwrap();
+        assert!(!package.urls().is_empty());
+    }
+}
+
+#[test]
+fn merge_catalog_test() {
+    let mut catalog = ReleaseCatalog::bundled();
+    let user_catalog = ReleaseCatalog::from_json(
+        r#"{
+            "default": "r26d",
+            "releases": [
+                {
+                    "version": "r21b",
+                    "hosts": {
+                        "linux": { "urls": ["http://mirror.local/ndk-r21b.zip"], "sha1": "abc" }
+                    }
+                },
+                {
+                    "version": "r26d",
+                    "revision": "26.3.11579264",
+                    "hosts": {
+                        "linux": { "urls": ["http://mirror.local/ndk-r26d.zip"] }
+                    }
+                }
+            ]
+        }"#,
+    )
+    .unwrap();
+    catalog.merge(user_catalog);
+
+    assert_eq!(catalog.default_version(), Some("r26d"));
+    assert!(catalog.release("26.3.11579264").is_some());
+
+    let r21b = catalog.release("r21b").unwrap().package("linux").unwrap();
+    assert_eq!(
+        r21b.urls(),
+        &["http://mirror.local/ndk-r21b.zip".to_owned()]
+    );
+    assert_eq!(r21b.sha1(), Some("abc"));
+}
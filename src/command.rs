@@ -1,95 +1,840 @@
-use std::{
-    collections::HashMap,
-    path::{Path, PathBuf},
+use std::path::{Path, PathBuf};
+
+use super::{
+    config::Config,
+    ndk::NdkVersionReq,
+    platform::{ApiLevels, LinkArgs, TargetSelection},
+    project::{self, Project},
+    prompt,
 };
 
 pub enum CommandState {
-    Options(CommandOptions),
+    Options(Box<CommandOptions>),
     ExitWithPrint,
+    /// The arguments are wrong, the reason is printed by the caller.
+    Invalid(String),
+}
+
+/// What andrust is asked to do, by the first arguments which are not options.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Command {
+    /// Sets up the NDK and the cargo config, which is the default.
+    Setup,
+    Ndk(NdkCommand),
+    Build,
+    Doctor,
+    Env,
+    Clean,
+    Restore(RestoreTarget),
+    /// Removes the targets managed by andrust from the cargo config.
+    Unconfigure,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum NdkCommand {
+    /// Installs the NDK of `--ndk-version` or the `<version>` operand.
+    Install,
+    List,
+    Remove(String),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum RestoreTarget {
+    Latest,
+    List,
+    Id(String),
+}
+
+struct OptionSpec {
+    short: Option<&'static str>,
+    long: &'static str,
+    /// The name of the value, `None` for a switch.
+    value: Option<&'static str>,
+    /// Whether the option can be given more than once.
+    repeatable: bool,
+    help: &'static str,
+}
+
+struct CommandSpec {
+    /// Words selecting the command, e.g. `["ndk", "install"]`.
+    words: &'static [&'static str],
+    operands: &'static str,
+    /// The most operands the command takes.
+    max_operands: usize,
+    about: &'static str,
+    options: &'static [&'static OptionSpec],
 }
 
+static ROOT: OptionSpec = OptionSpec {
+    short: Some("-r"),
+    long: "--root",
+    value: Some("<DIR>"),
+    repeatable: false,
+    help: "Set rust project root directory, default is the nearest Cargo.toml or workspace root",
+};
+static MANIFEST_PATH: OptionSpec = OptionSpec {
+    short: None,
+    long: "--manifest-path",
+    value: Some("<PATH>"),
+    repeatable: false,
+    help: "Set the Cargo.toml of the project, as cargo does",
+};
+static NDK: OptionSpec = OptionSpec {
+    short: Some("-n"),
+    long: "--ndk",
+    value: Some("<DIR>"),
+    repeatable: false,
+    help: "Set NDK home directory",
+};
+static NDK_VERSION: OptionSpec = OptionSpec {
+    short: None,
+    long: "--ndk-version",
+    value: Some("<REQ>"),
+    repeatable: false,
+    help: r#"Require an NDK version, e.g. 25.2.9519653, r25c or ">=23""#,
+};
+static TARGETS: OptionSpec = OptionSpec {
+    short: Some("-t"),
+    long: "--targets",
+    value: Some("<TARGETS>"),
+    repeatable: true,
+    help: "Set targets by triple, ABI or architecture, e.g. aarch64,armeabi-v7a",
+};
+static API_LEVEL: OptionSpec = OptionSpec {
+    short: None,
+    long: "--api-level",
+    value: Some("<LEVELS>"),
+    repeatable: true,
+    help: "Set the API level globally or per target, e.g. 24 or 24,arm64-v8a=26",
+};
+static DRY_RUN: OptionSpec = OptionSpec {
+    short: None,
+    long: "--dry-run",
+    value: None,
+    repeatable: false,
+    help: "Print the NDK root, targets and cargo config without writing",
+};
+static DIFF: OptionSpec = OptionSpec {
+    short: None,
+    long: "--diff",
+    value: None,
+    repeatable: false,
+    help: "Print the changes to the cargo config without writing",
+};
+static LIST: OptionSpec = OptionSpec {
+    short: None,
+    long: "--list",
+    value: None,
+    repeatable: false,
+    help: "List the backups instead of restoring one",
+};
+static QUIET: OptionSpec = OptionSpec {
+    short: Some("-q"),
+    long: "--quiet",
+    value: None,
+    repeatable: false,
+    help: "Do not show download progress",
+};
+static RELEASE: OptionSpec = OptionSpec {
+    short: None,
+    long: "--release",
+    value: None,
+    repeatable: false,
+    help: "Build with the release profile",
+};
+static JOBS: OptionSpec = OptionSpec {
+    short: Some("-j"),
+    long: "--jobs",
+    value: Some("<N>"),
+    repeatable: false,
+    help: "Number of targets built at once, 1 by default",
+};
+static WIZARD: OptionSpec = OptionSpec {
+    short: None,
+    long: "--wizard",
+    value: None,
+    repeatable: false,
+    help: "Choose the NDK, targets and API level in a terminal wizard",
+};
+static NON_INTERACTIVE: OptionSpec = OptionSpec {
+    short: None,
+    long: "--non-interactive",
+    value: None,
+    repeatable: false,
+    help: "Fail instead of asking, also when stdin is not a terminal or CI is set",
+};
+static VERSION: OptionSpec = OptionSpec {
+    short: Some("-v"),
+    long: "--version",
+    value: None,
+    repeatable: false,
+    help: "Prints version information",
+};
+static HELP: OptionSpec = OptionSpec {
+    short: Some("-h"),
+    long: "--help",
+    value: None,
+    repeatable: false,
+    help: "Prints help information",
+};
+
+static ALL_OPTIONS: [&OptionSpec; 16] = [
+    &ROOT,
+    &MANIFEST_PATH,
+    &NDK,
+    &NDK_VERSION,
+    &TARGETS,
+    &API_LEVEL,
+    &DRY_RUN,
+    &DIFF,
+    &WIZARD,
+    &RELEASE,
+    &JOBS,
+    &LIST,
+    &QUIET,
+    &NON_INTERACTIVE,
+    &VERSION,
+    &HELP,
+];
+
+/// Options of every command.
+static GLOBAL_OPTIONS: [&OptionSpec; 6] = [
+    &ROOT,
+    &MANIFEST_PATH,
+    &QUIET,
+    &NON_INTERACTIVE,
+    &VERSION,
+    &HELP,
+];
+
+static SETUP_OPTIONS: [&OptionSpec; 7] = [
+    &NDK,
+    &NDK_VERSION,
+    &TARGETS,
+    &API_LEVEL,
+    &DRY_RUN,
+    &DIFF,
+    &WIZARD,
+];
+
+static COMMANDS: [CommandSpec; 10] = [
+    CommandSpec {
+        words: &["setup"],
+        operands: "",
+        max_operands: 0,
+        about: "Set up the NDK and the cargo config, the default command",
+        options: &SETUP_OPTIONS,
+    },
+    CommandSpec {
+        words: &["ndk", "install"],
+        operands: " [<version>]",
+        max_operands: 1,
+        about: "Install an NDK from the catalog, the default or newest matching one",
+        options: &[&NDK_VERSION],
+    },
+    CommandSpec {
+        words: &["ndk", "list"],
+        operands: "",
+        max_operands: 0,
+        about: "List the NDKs found on this machine",
+        options: &[],
+    },
+    CommandSpec {
+        words: &["ndk", "remove"],
+        operands: " <version>",
+        max_operands: 1,
+        about: "Remove an NDK installed by andrust",
+        options: &[],
+    },
+    CommandSpec {
+        words: &["build"],
+        operands: " [-- <cargo args>...]",
+        max_operands: 0,
+        about: "Build the project for every target",
+        options: &[&TARGETS, &RELEASE, &JOBS],
+    },
+    CommandSpec {
+        words: &["doctor"],
+        operands: "",
+        max_operands: 0,
+        about: "Check cargo, rustup targets, the NDK and the cargo config",
+        options: &[&NDK, &NDK_VERSION, &TARGETS, &API_LEVEL],
+    },
+    CommandSpec {
+        words: &["env"],
+        operands: "",
+        max_operands: 0,
+        about: "Print the environment variables of the toolsets for a shell",
+        options: &[&NDK, &NDK_VERSION, &TARGETS, &API_LEVEL],
+    },
+    CommandSpec {
+        words: &["clean"],
+        operands: "",
+        max_operands: 0,
+        about: "Remove downloaded NDK archives and partial downloads",
+        options: &[],
+    },
+    CommandSpec {
+        words: &["restore"],
+        operands: " [<id>]",
+        max_operands: 1,
+        about: "Restore the latest or <id> backup of the cargo config",
+        options: &[&LIST],
+    },
+    CommandSpec {
+        words: &["unconfigure"],
+        operands: "",
+        max_operands: 0,
+        about: "Remove the targets managed by andrust from the cargo config",
+        options: &[],
+    },
+];
+
+/// Whether `setup` runs the wizard.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Wizard {
+    /// On the first run of a project in a terminal, see `wizard::should_run`.
+    Auto,
+    /// `--wizard`
+    Always,
+    /// The NDK, targets or API level are given on the command line.
+    Never,
+}
+
+#[derive(Clone)]
 pub struct CommandOptions {
+    command: Command,
     proj_root: Option<PathBuf>,
+    manifest_path: Option<PathBuf>,
+    target_dir: Option<PathBuf>,
     ndk_root: Option<PathBuf>,
+    ndk_version: Option<NdkVersionReq>,
+    api_levels: ApiLevels,
+    targets: Option<TargetSelection>,
+    link_args: LinkArgs,
+    jni_libs: Option<PathBuf>,
+    sdk_root: Option<PathBuf>,
+    mirrors: Vec<String>,
+    proxy: Option<String>,
+    dry_run: bool,
+    diff: bool,
+    quiet: bool,
+    non_interactive: bool,
+    wizard: Wizard,
+    confirm_write: bool,
+    release: bool,
+    jobs: usize,
+    cargo_args: Vec<String>,
 }
 
 impl Default for CommandOptions {
     fn default() -> Self {
         CommandOptions {
+            command: Command::Setup,
             proj_root: None,
+            manifest_path: None,
+            target_dir: None,
             ndk_root: None,
+            ndk_version: None,
+            api_levels: ApiLevels::default(),
+            targets: None,
+            link_args: LinkArgs::default(),
+            jni_libs: None,
+            sdk_root: None,
+            mirrors: Vec::new(),
+            proxy: None,
+            dry_run: false,
+            diff: false,
+            quiet: false,
+            non_interactive: false,
+            wizard: Wizard::Auto,
+            confirm_write: false,
+            release: false,
+            jobs: 1,
+            cargo_args: Vec::new(),
         }
     }
 }
 
 impl CommandState {
     pub fn new() -> Self {
-        let args = CommandState::parse_args();
-        CommandState::from(args)
+        let mut args = std::env::args();
+        let program = args.next().unwrap_or_default();
+        let args = project::strip_cargo_subcommand(program.as_str(), args.collect());
+        match CommandState::from_args(args) {
+            CommandState::Options(mut cmd_opts) => {
+                cmd_opts.non_interactive |= prompt::detect_non_interactive();
+                CommandState::Options(cmd_opts)
+            }
+            state => state,
+        }
     }
 
-    pub fn from(command_map: HashMap<String, String>) -> Self {
-        let mut proj_root: Option<PathBuf> = None;
-        let mut ndk_home: Option<PathBuf> = None;
+    /// Parses the arguments after the program name.
+    pub fn from_args<I, S>(args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut args = args.into_iter().map(Into::into);
+        let mut operands: Vec<String> = Vec::new();
+        let mut given: Vec<(&'static OptionSpec, String)> = Vec::new();
+        let mut unknown: Vec<String> = Vec::new();
 
-        for (opt, obj) in command_map {
-            match opt.as_str() {
-                "-r" | "--root" => proj_root = Some(PathBuf::from(obj.as_str())),
-                "-n" | "--ndk" => ndk_home = Some(PathBuf::from(obj.as_str())),
-                "-v" | "--version" => {
-                    show_version();
-                    return CommandState::ExitWithPrint;
+        let mut trailing: Vec<String> = Vec::new();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                trailing.extend(args.by_ref());
+                break;
+            }
+            if !arg.starts_with('-') || arg == "-" {
+                operands.push(arg);
+                continue;
+            }
+
+            let (name, inline_value) = match arg.find('=') {
+                Some(index) if arg.starts_with("--") => {
+                    (arg[..index].to_owned(), Some(arg[index + 1..].to_owned()))
                 }
-                "-h" | "--help" => {
-                    show_help();
-                    return CommandState::ExitWithPrint;
+                _ => (arg.clone(), None),
+            };
+            let spec = match ALL_OPTIONS
+                .iter()
+                .find(|spec| spec.long == name || spec.short == Some(name.as_str()))
+            {
+                Some(spec) => *spec,
+                None => {
+                    unknown.push(name);
+                    continue;
                 }
-                _ => (),
+            };
+
+            let value = match (spec.value, inline_value) {
+                (None, None) => String::new(),
+                (None, Some(_)) => {
+                    return CommandState::Invalid(format!("{} does not take a value", spec.long))
+                }
+                (Some(_), Some(value)) => value,
+                (Some(value_name), None) => match args.next() {
+                    Some(value) => value,
+                    None => {
+                        return CommandState::Invalid(format!(
+                            "{} requires {}",
+                            spec.long, value_name
+                        ))
+                    }
+                },
+            };
+            given.push((spec, value));
+        }
+
+        // The arguments after `--` of `build` are passed to cargo.
+        let cargo_args = if operands.first().map(String::as_str) == Some("build") {
+            trailing
+        } else {
+            operands.extend(trailing);
+            Vec::new()
+        };
+        let help = given.iter().any(|(spec, _)| spec.long == HELP.long);
+        let is_ndk = operands.first().map(String::as_str) == Some("ndk");
+        let found = find_command(operands);
+        // `andrust ndk --help` has no action yet, so it shows the ndk commands.
+        if help && is_ndk && found.is_err() {
+            show_ndk_help();
+            return CommandState::ExitWithPrint;
+        }
+        if let Some(name) = unknown.first() {
+            let options = match &found {
+                Ok((command_spec, _)) => allowed_options(*command_spec),
+                Err(_) => ALL_OPTIONS.to_vec(),
+            };
+            let names = options
+                .iter()
+                .flat_map(|spec| spec.short.into_iter().chain(Some(spec.long)));
+            return CommandState::Invalid(with_suggestion(
+                format!("unknown option {}", name),
+                name.as_str(),
+                names,
+            ));
+        }
+        let (command_spec, operands) = match found {
+            Ok(found) => found,
+            Err(reason) => return CommandState::Invalid(reason),
+        };
+
+        if help {
+            show_help(command_spec);
+            return CommandState::ExitWithPrint;
+        }
+        if given.iter().any(|(spec, _)| spec.long == VERSION.long) {
+            show_version();
+            return CommandState::ExitWithPrint;
+        }
+
+        let allowed = allowed_options(command_spec);
+        let command_name = command_spec
+            .map(|spec| spec.words.join(" "))
+            .unwrap_or_else(|| "setup".to_owned());
+        for (index, (spec, _)) in given.iter().enumerate() {
+            if !allowed.iter().any(|allowed| allowed.long == spec.long) {
+                return CommandState::Invalid(format!(
+                    "{} is not an option of {}",
+                    spec.long, command_name
+                ));
+            }
+            let is_repeated = given[..index]
+                .iter()
+                .any(|(previous, _)| previous.long == spec.long);
+            if is_repeated && !spec.repeatable {
+                return CommandState::Invalid(format!("{} is given more than once", spec.long));
             }
         }
 
-        CommandState::Options(CommandOptions {
-            proj_root,
-            ndk_root: ndk_home,
-        })
-    }
+        let max_operands = command_spec.map_or(0, |spec| spec.max_operands);
+        if operands.len() > max_operands {
+            return CommandState::Invalid(format!(
+                "unexpected argument {} for {}",
+                operands[max_operands], command_name
+            ));
+        }
 
-    fn parse_args() -> HashMap<String, String> {
-        use std::env;
+        let command = match command_spec.map(|spec| spec.words) {
+            None | Some(["setup"]) => Command::Setup,
+            Some(["ndk", "install"]) => Command::Ndk(NdkCommand::Install),
+            Some(["ndk", "list"]) => Command::Ndk(NdkCommand::List),
+            Some(["ndk", "remove"]) => match operands.first() {
+                Some(version) => Command::Ndk(NdkCommand::Remove(version.to_owned())),
+                None => return CommandState::Invalid("ndk remove requires <version>".to_owned()),
+            },
+            Some(["build"]) => Command::Build,
+            Some(["doctor"]) => Command::Doctor,
+            Some(["env"]) => Command::Env,
+            Some(["clean"]) => Command::Clean,
+            Some(["restore"]) if given.iter().any(|(spec, _)| spec.long == LIST.long) => {
+                Command::Restore(RestoreTarget::List)
+            }
+            Some(["restore"]) => match operands.first() {
+                Some(id) => Command::Restore(RestoreTarget::Id(id.to_owned())),
+                None => Command::Restore(RestoreTarget::Latest),
+            },
+            Some(["unconfigure"]) => Command::Unconfigure,
+            Some(words) => unreachable!("{:?} has no command", words),
+        };
 
-        let args = env::args();
+        let mut cmd_opts = CommandOptions {
+            command,
+            cargo_args,
+            ..CommandOptions::default()
+        };
+        if let Command::Ndk(NdkCommand::Install) = cmd_opts.command {
+            if let Some(version) = operands.first() {
+                given.push((&NDK_VERSION, version.to_owned()));
+            }
+        }
 
-        let mut commands = HashMap::new();
-        let mut opt: Option<String> = None;
-        for arg in args {
-            if opt == None && arg.as_str().starts_with("-") {
-                opt = Some(arg);
-            } else if opt != None {
-                commands.insert(opt.unwrap(), arg);
-                opt = None;
+        let mut target_names: Vec<String> = Vec::new();
+        let mut api_levels: Vec<String> = Vec::new();
+        for (spec, value) in given {
+            if [NDK.long, NDK_VERSION.long, TARGETS.long, API_LEVEL.long].contains(&spec.long)
+                && cmd_opts.wizard == Wizard::Auto
+            {
+                cmd_opts.wizard = Wizard::Never;
+            }
+            match spec.long {
+                "--root" => cmd_opts.proj_root = Some(PathBuf::from(value)),
+                "--manifest-path" => cmd_opts.manifest_path = Some(PathBuf::from(value)),
+                "--ndk" => cmd_opts.ndk_root = Some(PathBuf::from(value)),
+                "--ndk-version" => match NdkVersionReq::parse(value.as_str()) {
+                    Some(requirement) => cmd_opts.ndk_version = Some(requirement),
+                    None => {
+                        return CommandState::Invalid(format!(
+                            "invalid NDK version requirement : {}",
+                            value
+                        ))
+                    }
+                },
+                "--targets" => target_names.extend(value.split(',').map(str::to_owned)),
+                "--api-level" => api_levels.push(value),
+                "--dry-run" => cmd_opts.dry_run = true,
+                "--diff" => cmd_opts.diff = true,
+                "--quiet" => cmd_opts.quiet = true,
+                "--non-interactive" => cmd_opts.non_interactive = true,
+                "--wizard" => cmd_opts.wizard = Wizard::Always,
+                "--release" => cmd_opts.release = true,
+                "--jobs" => match value.parse() {
+                    Ok(jobs) if jobs > 0 => cmd_opts.jobs = jobs,
+                    _ => {
+                        return CommandState::Invalid(format!("invalid number of jobs : {}", value))
+                    }
+                },
+                _ => (),
             }
         }
 
-        if opt != None {
-            commands.insert(opt.unwrap(), "".to_owned());
+        if !target_names.is_empty() {
+            match TargetSelection::from_names(target_names) {
+                Ok(selection) => cmd_opts.targets = Some(selection),
+                Err(reason) => {
+                    return CommandState::Invalid(format!("invalid targets : {}", reason))
+                }
+            }
         }
+        match ApiLevels::parse(api_levels.join(",").as_str()) {
+            Ok(levels) => cmd_opts.api_levels = levels,
+            Err(reason) => return CommandState::Invalid(format!("invalid API level : {}", reason)),
+        }
+
+        CommandState::Options(Box::new(cmd_opts))
+    }
+}
+
+/// The options of `command_spec`, `None` for the default command, and the global options.
+fn allowed_options(command_spec: Option<&CommandSpec>) -> Vec<&'static OptionSpec> {
+    GLOBAL_OPTIONS
+        .iter()
+        .chain(
+            command_spec
+                .map_or(&SETUP_OPTIONS[..], |spec| spec.options)
+                .iter(),
+        )
+        .copied()
+        .collect()
+}
+
+/// The command named by the leading operands, `None` for the default command.
+fn find_command(
+    mut operands: Vec<String>,
+) -> Result<(Option<&'static CommandSpec>, Vec<String>), String> {
+    let first = match operands.first() {
+        Some(first) => first.clone(),
+        None => return Ok((None, operands)),
+    };
+
+    if first == "ndk" {
+        let action = operands.get(1).cloned().unwrap_or_default();
+        let spec = COMMANDS
+            .iter()
+            .find(|spec| spec.words == ["ndk", action.as_str()]);
+        return match spec {
+            Some(spec) => Ok((Some(spec), operands.split_off(2))),
+            None => Err(with_suggestion(
+                format!("ndk requires install, list or remove, not {:?}", action),
+                action.as_str(),
+                vec!["install", "list", "remove"],
+            )),
+        };
+    }
+
+    match COMMANDS.iter().find(|spec| spec.words == [first.as_str()]) {
+        Some(spec) => Ok((Some(spec), operands.split_off(1))),
+        None => Err(with_suggestion(
+            format!("unknown command {}", first),
+            first.as_str(),
+            COMMANDS.iter().map(|spec| spec.words[0]),
+        )),
+    }
+}
+
+/// Appends the candidate closest to `given` to `message`, if any is close enough.
+fn with_suggestion<'a, I>(message: String, given: &str, candidates: I) -> String
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let closest = candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(given, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= 2.max(candidate.len() / 3))
+        .min_by_key(|(distance, _)| *distance);
+    match closest {
+        Some((_, candidate)) => format!("{}, did you mean {}?", message, candidate),
+        None => message,
+    }
+}
 
-        commands
+/// Levenshtein distance between `lhs` and `rhs`.
+pub fn edit_distance(lhs: &str, rhs: &str) -> usize {
+    let rhs: Vec<char> = rhs.chars().collect();
+    let mut previous: Vec<usize> = (0..=rhs.len()).collect();
+    for (i, lhs_char) in lhs.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, rhs_char) in rhs.iter().enumerate() {
+            let substitution = previous[j] + if lhs_char == *rhs_char { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
     }
+    previous[rhs.len()]
 }
 
 impl CommandOptions {
+    pub fn command(&self) -> &Command {
+        &self.command
+    }
+
     pub fn proj_root(&self) -> Option<&Path> {
-        self.proj_root.as_ref().map(|root| root.as_path())
+        self.proj_root.as_deref()
+    }
+
+    pub fn manifest_path(&self) -> Option<&Path> {
+        self.manifest_path.as_deref()
+    }
+
+    /// Where cargo puts the build output, known after the project is located.
+    pub fn target_dir(&self) -> Option<&Path> {
+        self.target_dir.as_deref()
+    }
+
+    pub fn set_project(&mut self, project: &Project) {
+        self.proj_root = Some(project.root().to_path_buf());
+        self.target_dir = Some(project.target_dir().to_path_buf());
     }
 
     pub fn ndk_root(&self) -> Option<&Path> {
-        self.ndk_root.as_ref().map(|home| home.as_path())
+        self.ndk_root.as_deref()
+    }
+
+    pub fn ndk_version(&self) -> Option<&NdkVersionReq> {
+        self.ndk_version.as_ref()
+    }
+
+    pub fn api_levels(&self) -> &ApiLevels {
+        &self.api_levels
+    }
+
+    pub fn targets(&self) -> Option<&TargetSelection> {
+        self.targets.as_ref()
+    }
+
+    /// Fills the options which are not given on the command line from `config`, the
+    /// environment variables, `andrust.toml` and the user config merged by
+    /// `Config::layered`.
+    pub fn apply_config(&mut self, config: &Config) -> Result<(), String> {
+        if let (None, Some(names)) = (&self.targets, config.targets()) {
+            self.targets = Some(TargetSelection::from_names(names)?);
+        }
+        if let (None, Some(ndk_root)) = (&self.ndk_root, config.ndk_root()) {
+            self.ndk_root = Some(ndk_root.to_path_buf());
+        }
+        if let (None, Some(version)) = (&self.ndk_version, config.ndk_version()) {
+            self.ndk_version = Some(
+                NdkVersionReq::parse(version)
+                    .ok_or_else(|| format!("invalid NDK version requirement : {}", version))?,
+            );
+        }
+
+        let mut api_levels = ApiLevels::default();
+        if let Some(level) = config.api_level() {
+            api_levels.set_default(level);
+        }
+        for (name, target) in config.target_configs() {
+            if let Some(level) = target.api_level() {
+                api_levels.set(name, level)?;
+            }
+            if let Some(args) = target.link_args() {
+                self.link_args.set(name, args)?;
+            }
+        }
+        self.api_levels.fill_from(api_levels);
+        if let Some(args) = config.link_args() {
+            self.link_args.set_common(args);
+        }
+
+        if let Some(target_dir) = config.target_dir() {
+            self.target_dir = Some(target_dir.to_path_buf());
+        }
+        if let Some(jni_libs) = config.jni_libs() {
+            self.jni_libs = Some(jni_libs.to_path_buf());
+        }
+
+        self.sdk_root = config.sdk_root().map(Path::to_path_buf);
+        self.mirrors = config.mirrors().map(<[String]>::to_vec).unwrap_or_default();
+        self.proxy = config.proxy().map(str::to_owned);
+        Ok(())
+    }
+
+    pub fn link_args(&self) -> &LinkArgs {
+        &self.link_args
+    }
+
+    /// Where `andrust build` copies the shared libraries, in a directory per ABI.
+    pub fn jni_libs(&self) -> Option<&Path> {
+        self.jni_libs.as_deref()
+    }
+
+    /// The Android SDK of the config, whose NDKs are searched besides `ANDROID_HOME`.
+    pub fn sdk_root(&self) -> Option<&Path> {
+        self.sdk_root.as_deref()
+    }
+
+    /// Mirrors of the NDK downloads, tried before the urls of the catalog.
+    pub fn mirrors(&self) -> &[String] {
+        self.mirrors.as_slice()
+    }
+
+    /// The HTTP proxy of the NDK downloads.
+    pub fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    pub fn diff(&self) -> bool {
+        self.diff
+    }
+
+    pub fn quiet(&self) -> bool {
+        self.quiet
+    }
+
+    /// Whether andrust may ask on stdin, see `prompt::detect_non_interactive`.
+    pub fn interactive(&self) -> bool {
+        !self.non_interactive
+    }
+
+    pub fn wizard(&self) -> Wizard {
+        self.wizard
+    }
+
+    pub fn set_ndk_root(&mut self, ndk_root: PathBuf) {
+        self.ndk_root = Some(ndk_root);
+    }
+
+    pub fn set_targets(&mut self, targets: TargetSelection) {
+        self.targets = Some(targets);
+    }
+
+    pub fn set_api_levels(&mut self, api_levels: ApiLevels) {
+        self.api_levels = api_levels;
+    }
+
+    /// Whether the cargo config is previewed and confirmed before it is written.
+    pub fn confirm_write(&self) -> bool {
+        self.confirm_write
+    }
+
+    pub fn set_confirm_write(&mut self, confirm_write: bool) {
+        self.confirm_write = confirm_write;
+    }
+
+    pub fn release(&self) -> bool {
+        self.release
+    }
+
+    /// Number of targets `build` builds at once.
+    pub fn jobs(&self) -> usize {
+        self.jobs
+    }
+
+    /// Arguments after `--` of `build`, which are passed to cargo.
+    pub fn cargo_args(&self) -> &[String] {
+        self.cargo_args.as_slice()
     }
 }
 
 pub fn show_version() {
-    const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+    const VERSION: &str = env!("CARGO_PKG_VERSION");
     println!(
         r#"andrust {}
         "#,
@@ -97,18 +842,89 @@ pub fn show_version() {
     );
 }
 
-pub fn show_help() {
+fn format_options(options: &[&OptionSpec]) -> String {
+    options
+        .iter()
+        .map(|spec| {
+            let short = spec.short.map(|short| format!("{},", short));
+            let name = format!(
+                "{:>3} {}{}",
+                short.unwrap_or_default(),
+                spec.long,
+                spec.value
+                    .map(|value| format!(" {}", value))
+                    .unwrap_or_default()
+            );
+            format!("    {:<28}{}\n", name, spec.help)
+        })
+        .collect()
+}
+
+fn format_commands<'a, I>(commands: I) -> String
+where
+    I: IntoIterator<Item = &'a CommandSpec>,
+{
+    commands
+        .into_iter()
+        .map(|spec| {
+            let usage = format!("{}{}", spec.words.join(" "), spec.operands);
+            format!("    {:<28}{}\n", usage, spec.about)
+        })
+        .collect()
+}
+
+/// Prints the commands of `andrust ndk`.
+fn show_ndk_help() {
     println!(
-        r#"andrust is a helper to set up android cross compilation configuration for rust project
+        r#"Installs, lists and removes NDKs
 
 USAGE:
-    andrust [OPTIONS] [OBJECT]
+    andrust ndk <COMMAND> [OPTIONS]
 
-OPTIONS:
-    -r, --root              Set rust project root directory, default path is .
-    -h, --home              Set NDK home directory
-    -v, --version           Prints version information
-    -h, --help              Prints help information
-    "#
+COMMANDS:
+{}
+See andrust ndk <COMMAND> --help for the options of a command."#,
+        format_commands(COMMANDS.iter().filter(|spec| spec.words[0] == "ndk"))
     );
 }
+
+/// Prints the help of `command`, or of andrust when it is `None`.
+fn show_help(command: Option<&CommandSpec>) {
+    match command {
+        None => {
+            let commands = format_commands(COMMANDS.iter());
+            println!(
+                r#"andrust is a helper to set up android cross compilation configuration for rust project
+
+USAGE:
+    andrust [COMMAND] [OPTIONS]
+
+COMMANDS:
+{}
+OPTIONS:
+{}{}
+See andrust <COMMAND> --help for the options of a command."#,
+                commands,
+                format_options(&GLOBAL_OPTIONS),
+                format_options(&SETUP_OPTIONS)
+            );
+        }
+        Some(spec) => {
+            let mut options: Vec<&OptionSpec> = spec.options.to_vec();
+            options.extend(GLOBAL_OPTIONS.iter());
+            println!(
+                r#"{}
+
+USAGE:
+    andrust {} [OPTIONS]{}
+
+OPTIONS:
+{}"#,
+                spec.about,
+                spec.words.join(" "),
+                spec.operands,
+                format_options(&options)
+            );
+        }
+    }
+}
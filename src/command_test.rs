@@ -0,0 +1,93 @@
+use super::{
+    command::{edit_distance, Command, CommandState, NdkCommand, RestoreTarget},
+    platform::{LinuxConfig, Platform, TargetSpec},
+};
+
+fn parse(args: &[&str]) -> Result<Command, String> {
+    match CommandState::from_args(args.iter().copied()) {
+        CommandState::Options(cmd_opts) => Ok(cmd_opts.command().clone()),
+        CommandState::Invalid(reason) => Err(reason),
+        CommandState::ExitWithPrint => Err("exit".to_owned()),
+    }
+}
+
+#[test]
+fn subcommand_test() {
+    assert_eq!(parse(&[]), Ok(Command::Setup));
+    assert_eq!(parse(&["-n", "/opt/ndk"]), Ok(Command::Setup));
+    assert_eq!(parse(&["setup", "--dry-run"]), Ok(Command::Setup));
+    assert_eq!(
+        parse(&["ndk", "install", "r25c"]),
+        Ok(Command::Ndk(NdkCommand::Install))
+    );
+    assert_eq!(parse(&["ndk", "list"]), Ok(Command::Ndk(NdkCommand::List)));
+    assert_eq!(
+        parse(&["ndk", "remove", "25.2.9519653"]),
+        Ok(Command::Ndk(NdkCommand::Remove("25.2.9519653".to_owned())))
+    );
+    assert_eq!(parse(&["build", "-t", "aarch64"]), Ok(Command::Build));
+    assert_eq!(parse(&["doctor"]), Ok(Command::Doctor));
+    assert_eq!(parse(&["env", "--api-level=24"]), Ok(Command::Env));
+    assert_eq!(parse(&["clean", "-q"]), Ok(Command::Clean));
+    assert_eq!(
+        parse(&["restore", "--list"]),
+        Ok(Command::Restore(RestoreTarget::List))
+    );
+    assert_eq!(
+        parse(&["restore", "20261018-153012"]),
+        Ok(Command::Restore(RestoreTarget::Id(
+            "20261018-153012".to_owned()
+        )))
+    );
+    assert_eq!(parse(&["unconfigure"]), Ok(Command::Unconfigure));
+    assert_eq!(parse(&["--help"]), Err("exit".to_owned()));
+    assert_eq!(parse(&["ndk", "remove", "-h"]), Err("exit".to_owned()));
+    assert_eq!(parse(&["ndk", "--help"]), Err("exit".to_owned()));
+    assert_eq!(parse(&["ndk", "-h"]), Err("exit".to_owned()));
+}
+
+#[test]
+fn invalid_args_test() {
+    assert_eq!(
+        parse(&["--tragets", "aarch64"]),
+        Err("unknown option --tragets, did you mean --targets?".to_owned())
+    );
+    assert_eq!(
+        parse(&["doctr"]),
+        Err("unknown command doctr, did you mean doctor?".to_owned())
+    );
+    assert!(parse(&["ndk"]).is_err());
+    assert!(parse(&["ndk", "remove"]).is_err());
+    assert!(parse(&["clean", "--dry-run"]).is_err());
+    assert!(parse(&["setup", "extra"]).is_err());
+    assert!(parse(&["--root"]).is_err());
+    assert!(parse(&["--quiet=yes"]).is_err());
+    assert!(parse(&["-r", "a", "--root", "b"]).is_err());
+    assert!(parse(&["--ndk-version", "newest"]).is_err());
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+}
+
+#[test]
+fn repeated_options_test() {
+    let args = [
+        "-t",
+        "aarch64",
+        "--targets",
+        "x86_64",
+        "--api-level",
+        "24",
+        "--api-level",
+        "x86_64=30",
+    ];
+    let cmd_opts = match CommandState::from_args(args.iter().copied()) {
+        CommandState::Options(cmd_opts) => cmd_opts,
+        _ => panic!("invalid options"),
+    };
+    let selection = cmd_opts.targets().unwrap();
+    let spec = |name| TargetSpec::find(name).unwrap();
+    let toolsets = LinuxConfig::selected_toolsets(Some(&cmd_opts));
+    assert_eq!(toolsets.len(), 2);
+    assert!(toolsets.iter().all(|target| selection.contains(target)));
+    assert_eq!(cmd_opts.api_levels().level(spec("aarch64")), Some(24));
+    assert_eq!(cmd_opts.api_levels().level(spec("x86_64")), Some(30));
+}
@@ -1,2 +1,353 @@
-//TODO: target ar, linker should be set here
-pub struct Config {}
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use super::platform::TargetSpec;
+
+pub const CONFIG_FILE: &str = "andrust.toml";
+
+/// Settings of andrust, from `andrust.toml` of the project, the user config or `ANDRUST_*`
+/// environment variables.
+///
+/// Every setting is optional, so a layer only overrides what it sets. See
+/// `source_config/andrust.example.toml` for every key.
+#[derive(Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    targets: Option<Vec<String>>,
+    api_level: Option<u32>,
+    link_args: Option<Vec<String>>,
+    ndk: NdkConfig,
+    sdk: SdkConfig,
+    download: DownloadConfig,
+    output: OutputConfig,
+    /// Settings of a target, keyed by its triple. A file may name it by its ABI or
+    /// architecture as well.
+    target: BTreeMap<String, TargetConfig>,
+}
+
+#[derive(Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct NdkConfig {
+    root: Option<PathBuf>,
+    version: Option<String>,
+}
+
+/// The Android SDK, which usually belongs in the user config as it differs per machine.
+#[derive(Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SdkConfig {
+    root: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct DownloadConfig {
+    /// Base urls which are tried before the urls of the catalog.
+    mirrors: Option<Vec<String>>,
+    /// e.g. `http://proxy.example.com:3128`
+    proxy: Option<String>,
+}
+
+#[derive(Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct OutputConfig {
+    /// `--target-dir` of cargo, which `CARGO_TARGET_DIR` overrides.
+    target_dir: Option<PathBuf>,
+    /// Where `andrust build` copies the shared libraries, in a directory per ABI.
+    jni_libs: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Default, Clone, PartialEq, Debug)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TargetConfig {
+    api_level: Option<u32>,
+    link_args: Option<Vec<String>>,
+}
+
+impl Config {
+    /// `andrust.toml` of the project, which is `None` when the project has none.
+    pub fn load(proj_root: &Path) -> Result<Option<Self>, String> {
+        Config::load_file(proj_root.join(CONFIG_FILE).as_path())
+    }
+
+    /// The user config, `andrust/config.toml` in the user config directory.
+    pub fn load_user() -> Result<Option<Self>, String> {
+        match user_config_path() {
+            Some(path) => Config::load_file(path.as_path()),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the config at `path`, relative paths in it are relative to its directory.
+    ///
+    /// A missing file is `None`, and a file which can not be read or parsed is an error.
+    pub fn load_file(path: &Path) -> Result<Option<Self>, String> {
+        let config = match fs::read_to_string(path) {
+            Ok(config) => config,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(format!("{} : {}", path.display(), err)),
+        };
+        let config = toml::from_str::<Config>(config.as_str())
+            .map_err(|err| err.to_string())
+            .and_then(Config::keyed_by_triple)
+            .map_err(|err| format!("{} : {}", path.display(), err))?;
+        Ok(Some(config.relative_to(
+            path.parent().unwrap_or_else(|| Path::new("")),
+        )))
+    }
+
+    /// Renames `[target.<name>]` to the triple of the target, so that the layers merge the
+    /// tables of a target however they name it.
+    fn keyed_by_triple(mut self) -> Result<Self, String> {
+        let mut target = BTreeMap::new();
+        for (name, config) in std::mem::take(&mut self.target) {
+            let triple = TargetSpec::find(name.as_str())
+                .ok_or_else(|| format!("unknown target {}", name))?
+                .triple;
+            if target.insert(triple.to_owned(), config).is_some() {
+                return Err(format!("{} is set more than once in [target]", triple));
+            }
+        }
+        self.target = target;
+        Ok(self)
+    }
+
+    /// The settings of the `ANDRUST_*` environment variables, `CARGO_TARGET_DIR` and
+    /// `HTTPS_PROXY`.
+    ///
+    /// Relative paths in them are relative to the working directory, as in cargo.
+    pub fn from_env() -> Result<Self, String> {
+        let config =
+            Config::from_vars(|name| env::var(name).ok().filter(|value| !value.is_empty()))?;
+        Ok(match env::current_dir() {
+            Ok(current_dir) => config.relative_to(current_dir.as_path()),
+            Err(_) => config,
+        })
+    }
+
+    pub fn from_vars<F>(var: F) -> Result<Self, String>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let list = |value: String| {
+            value
+                .split(',')
+                .map(|item| item.trim().to_owned())
+                .filter(|item| !item.is_empty())
+                .collect()
+        };
+
+        let api_level = match var("ANDRUST_API_LEVEL") {
+            Some(level) => Some(
+                level
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("ANDRUST_API_LEVEL : {} is not an API level", level))?,
+            ),
+            None => None,
+        };
+
+        Ok(Config {
+            targets: var("ANDRUST_TARGETS").map(list),
+            api_level,
+            link_args: var("ANDRUST_LINK_ARGS")
+                .map(|args| args.split_whitespace().map(str::to_owned).collect()),
+            ndk: NdkConfig {
+                root: var("ANDRUST_NDK").map(PathBuf::from),
+                version: var("ANDRUST_NDK_VERSION"),
+            },
+            sdk: SdkConfig::default(),
+            download: DownloadConfig {
+                mirrors: None,
+                proxy: var("HTTPS_PROXY").or_else(|| var("https_proxy")),
+            },
+            output: OutputConfig {
+                // CARGO_TARGET_DIR beats target-dir of the config files, as it does in cargo.
+                target_dir: var("ANDRUST_TARGET_DIR")
+                    .or_else(|| var("CARGO_TARGET_DIR"))
+                    .map(PathBuf::from),
+                jni_libs: var("ANDRUST_JNI_LIBS").map(PathBuf::from),
+            },
+            target: BTreeMap::new(),
+        })
+    }
+
+    /// Every layer below the command line, the later layers override the former:
+    /// the user config, `andrust.toml` of the project and the environment variables.
+    pub fn layered(proj_root: &Path) -> Result<Self, String> {
+        let mut config = Config::load_user()?.unwrap_or_default();
+        if let Some(project) = Config::load(proj_root)? {
+            config = config.overridden_by(project);
+        }
+        Ok(config.overridden_by(Config::from_env()?))
+    }
+
+    /// The settings of `self` which `upper` does not set, and the settings of `upper`.
+    ///
+    /// The global `api-level` of `upper` also hides the per target levels of `self`.
+    pub fn overridden_by(self, upper: Config) -> Self {
+        let mut target = self.target;
+        if upper.api_level.is_some() {
+            for lower_target in target.values_mut() {
+                lower_target.api_level = None;
+            }
+        }
+        for (name, upper_target) in upper.target {
+            let lower_target = target.remove(name.as_str()).unwrap_or_default();
+            target.insert(
+                name,
+                TargetConfig {
+                    api_level: upper_target.api_level.or(lower_target.api_level),
+                    link_args: upper_target.link_args.or(lower_target.link_args),
+                },
+            );
+        }
+
+        Config {
+            targets: upper.targets.or(self.targets),
+            api_level: upper.api_level.or(self.api_level),
+            link_args: upper.link_args.or(self.link_args),
+            ndk: NdkConfig {
+                root: upper.ndk.root.or(self.ndk.root),
+                version: upper.ndk.version.or(self.ndk.version),
+            },
+            sdk: SdkConfig {
+                root: upper.sdk.root.or(self.sdk.root),
+            },
+            download: DownloadConfig {
+                mirrors: upper.download.mirrors.or(self.download.mirrors),
+                proxy: upper.download.proxy.or(self.download.proxy),
+            },
+            output: OutputConfig {
+                target_dir: upper.output.target_dir.or(self.output.target_dir),
+                jni_libs: upper.output.jni_libs.or(self.output.jni_libs),
+            },
+            target,
+        }
+    }
+
+    fn relative_to(mut self, base: &Path) -> Self {
+        let resolve = |path: Option<PathBuf>| path.map(|path| base.join(path));
+        self.ndk.root = resolve(self.ndk.root);
+        self.sdk.root = resolve(self.sdk.root);
+        self.output.target_dir = resolve(self.output.target_dir);
+        self.output.jni_libs = resolve(self.output.jni_libs);
+        self
+    }
+
+    pub fn targets(&self) -> Option<&[String]> {
+        self.targets.as_deref()
+    }
+
+    pub fn api_level(&self) -> Option<u32> {
+        self.api_level
+    }
+
+    pub fn link_args(&self) -> Option<&[String]> {
+        self.link_args.as_deref()
+    }
+
+    pub fn ndk_root(&self) -> Option<&Path> {
+        self.ndk.root.as_deref()
+    }
+
+    pub fn ndk_version(&self) -> Option<&str> {
+        self.ndk.version.as_deref()
+    }
+
+    pub fn sdk_root(&self) -> Option<&Path> {
+        self.sdk.root.as_deref()
+    }
+
+    pub fn mirrors(&self) -> Option<&[String]> {
+        self.download.mirrors.as_deref()
+    }
+
+    pub fn proxy(&self) -> Option<&str> {
+        self.download.proxy.as_deref()
+    }
+
+    pub fn target_dir(&self) -> Option<&Path> {
+        self.output.target_dir.as_deref()
+    }
+
+    pub fn jni_libs(&self) -> Option<&Path> {
+        self.output.jni_libs.as_deref()
+    }
+
+    /// `(name, settings)` of the `[target.<name>]` tables.
+    pub fn target_configs(&self) -> impl Iterator<Item = (&str, &TargetConfig)> {
+        self.target
+            .iter()
+            .map(|(name, target)| (name.as_str(), target))
+    }
+}
+
+impl TargetConfig {
+    pub fn api_level(&self) -> Option<u32> {
+        self.api_level
+    }
+
+    pub fn link_args(&self) -> Option<&[String]> {
+        self.link_args.as_deref()
+    }
+}
+
+/// Sets `[ndk] root` of the user config to `ndk_root`, keeping the other settings and
+/// comments, and returns the path of the user config.
+pub fn save_user_ndk_root(ndk_root: &Path) -> io::Result<PathBuf> {
+    let path = user_config_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no user config directory"))?;
+    let existing = match fs::read_to_string(&path) {
+        Ok(existing) => existing,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+    let content = set_ndk_root(existing.as_str(), ndk_root)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    if let Some(config_dir) = path.parent() {
+        fs::create_dir_all(config_dir)?;
+    }
+    fs::write(&path, content)?;
+    Ok(path)
+}
+
+/// `existing` config with `[ndk] root` set to `ndk_root`.
+pub fn set_ndk_root(existing: &str, ndk_root: &Path) -> Result<String, String> {
+    use toml_edit::{value, DocumentMut, Item, Table};
+
+    let mut document: DocumentMut = existing
+        .parse()
+        .map_err(|err: toml_edit::TomlError| err.to_string())?;
+    let ndk_root = ndk_root
+        .to_str()
+        .ok_or_else(|| format!("{} is not UTF-8", ndk_root.display()))?;
+    document
+        .entry("ndk")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_mut()
+        .ok_or_else(|| "ndk is not a table".to_owned())?
+        .insert("root", value(ndk_root));
+    Ok(document.to_string())
+}
+
+/// `andrust/config.toml` in `$XDG_CONFIG_HOME`, `~/.config` or `%APPDATA%` on Windows.
+pub fn user_config_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|config_home| !config_home.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            if cfg!(target_os = "windows") {
+                env::var_os("APPDATA").map(PathBuf::from)
+            } else {
+                env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+            }
+        })
+        .map(|config_home| config_home.join("andrust").join("config.toml"))
+}
@@ -0,0 +1,267 @@
+use std::path::Path;
+
+use super::{
+    command::{CommandOptions, CommandState},
+    config::{self, Config},
+    platform::{self, LinuxConfig, Platform, TargetSpec},
+};
+
+fn command_options(args: &[&str]) -> CommandOptions {
+    match CommandState::from_args(args.iter().copied()) {
+        CommandState::Options(opts) => *opts,
+        _ => panic!("invalid options {:?}", args),
+    }
+}
+
+fn parse(config: &str) -> Option<Config> {
+    toml::from_str(config).ok()
+}
+
+fn selected_abis(cmd_opts: &CommandOptions) -> Vec<&'static str> {
+    let mut abis: Vec<_> = LinuxConfig::selected_toolsets(Some(cmd_opts))
+        .iter()
+        .map(|target| target.abi())
+        .collect();
+    abis.sort();
+    abis
+}
+
+#[test]
+fn target_selection_test() {
+    let cmd_opts = command_options(&["--targets", "aarch64,armv7"]);
+    assert_eq!(selected_abis(&cmd_opts), vec!["arm64-v8a", "armeabi-v7a"]);
+
+    let cmd_opts = command_options(&["-t", "x86_64-linux-android, x86"]);
+    assert_eq!(selected_abis(&cmd_opts), vec!["x86", "x86_64"]);
+
+    let cmd_opts = command_options(&[]);
+    assert_eq!(
+        selected_abis(&cmd_opts),
+        vec!["arm64-v8a", "armeabi-v7a", "x86", "x86_64"]
+    );
+    let cmd_opts = command_options(&["--targets", "riscv64"]);
+    assert_eq!(selected_abis(&cmd_opts), vec!["riscv64"]);
+
+    assert!(matches!(
+        CommandState::from_args(vec!["--targets", "mips"]),
+        CommandState::Invalid(_)
+    ));
+}
+
+#[test]
+fn config_targets_test() {
+    let config = parse(r#"targets = ["arm64-v8a", "armeabi-v7a"]"#).unwrap();
+    let mut cmd_opts = command_options(&[]);
+    cmd_opts.apply_config(&config).unwrap();
+    assert_eq!(selected_abis(&cmd_opts), vec!["arm64-v8a", "armeabi-v7a"]);
+
+    // The command line wins over andrust.toml.
+    let mut cmd_opts = command_options(&["--targets", "x86_64"]);
+    cmd_opts.apply_config(&config).unwrap();
+    assert_eq!(selected_abis(&cmd_opts), vec!["x86_64"]);
+
+    let config = parse(r#"targets = ["sparc"]"#).unwrap();
+    assert!(command_options(&[]).apply_config(&config).is_err());
+    assert!(parse("target = 1").is_none());
+}
+
+#[test]
+fn config_layers_test() {
+    let example = include_str!("../source_config/andrust.example.toml");
+    let user = parse(example).unwrap();
+    assert_eq!(user.api_level(), Some(24));
+    assert_eq!(user.ndk_version(), Some(">=25, <27"));
+
+    let project = parse(
+        r#"
+        targets = ["x86_64"]
+
+        [target.arm64-v8a]
+        link-args = ["-Wl,-s"]
+        "#,
+    )
+    .unwrap();
+    let env = Config::from_vars(|name| match name {
+        "ANDRUST_API_LEVEL" => Some("28".to_owned()),
+        "ANDRUST_JNI_LIBS" => Some("jniLibs".to_owned()),
+        _ => None,
+    })
+    .unwrap();
+    let config = user.overridden_by(project).overridden_by(env);
+    assert_eq!(config.targets(), Some(&["x86_64".to_owned()][..]));
+    assert_eq!(config.ndk_version(), Some(">=25, <27"));
+    assert_eq!(config.jni_libs(), Some(Path::new("jniLibs")));
+
+    // The global level of the environment hides the per target level of the user config.
+    let mut cmd_opts = command_options(&["--targets", "aarch64"]);
+    cmd_opts.apply_config(&config).unwrap();
+    let spec = TargetSpec::find("arm64-v8a").unwrap();
+    assert_eq!(cmd_opts.api_levels().level(spec), Some(28));
+    assert_eq!(selected_abis(&cmd_opts), vec!["arm64-v8a"]);
+    assert_eq!(
+        cmd_opts.link_args().for_target(spec),
+        vec!["-Wl,-z,max-page-size=16384", "-Wl,-s"]
+    );
+
+    // The command line wins over every layer.
+    let mut cmd_opts = command_options(&["--api-level", "arm64-v8a=30", "--ndk-version", "r26b"]);
+    cmd_opts.apply_config(&config).unwrap();
+    assert_eq!(cmd_opts.api_levels().level(spec), Some(30));
+    let x86_64 = TargetSpec::find("x86_64").unwrap();
+    assert_eq!(cmd_opts.api_levels().level(x86_64), Some(28));
+    assert_eq!(
+        cmd_opts.ndk_version().map(|version| version.to_string()),
+        Some("r26b".to_owned())
+    );
+
+    assert!(parse("[ndk]\npath = \"\"").is_none());
+    let config = parse("[ndk]\nversion = \"latest\"").unwrap();
+    assert!(command_options(&[]).apply_config(&config).is_err());
+}
+
+#[test]
+fn target_dir_test() {
+    let project = parse("[output]\ntarget-dir = \"/project/out\"").unwrap();
+    let mut cmd_opts = command_options(&[]);
+    cmd_opts.apply_config(&project).unwrap();
+    assert_eq!(cmd_opts.target_dir(), Some(Path::new("/project/out")));
+
+    // CARGO_TARGET_DIR wins over andrust.toml, and ANDRUST_TARGET_DIR over both.
+    let cargo = Config::from_vars(|name| match name {
+        "CARGO_TARGET_DIR" => Some("/cargo/out".to_owned()),
+        _ => None,
+    })
+    .unwrap();
+    let config = project.clone().overridden_by(cargo.clone());
+    let mut cmd_opts = command_options(&[]);
+    cmd_opts.apply_config(&config).unwrap();
+    assert_eq!(cmd_opts.target_dir(), Some(Path::new("/cargo/out")));
+
+    let andrust = Config::from_vars(|name| match name {
+        "ANDRUST_TARGET_DIR" => Some("/andrust/out".to_owned()),
+        "CARGO_TARGET_DIR" => Some("/cargo/out".to_owned()),
+        _ => None,
+    })
+    .unwrap();
+    assert_eq!(
+        project.overridden_by(andrust).target_dir(),
+        Some(Path::new("/andrust/out"))
+    );
+}
+
+#[test]
+fn target_keys_test() {
+    let root = std::env::temp_dir().join("andrust_target_keys_test");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    let user_path = root.join("config.toml");
+    std::fs::write(&user_path, "[target.arm64-v8a]\napi-level = 23\n").unwrap();
+    std::fs::write(
+        root.join(config::CONFIG_FILE),
+        "[target.aarch64]\napi-level = 26\n",
+    )
+    .unwrap();
+
+    // The project names aarch64 by its architecture, and still beats the user config.
+    let user = Config::load_file(user_path.as_path()).unwrap().unwrap();
+    let project = Config::load(root.as_path()).unwrap().unwrap();
+    let config = user.overridden_by(project);
+    assert_eq!(config.target_configs().count(), 1);
+    let mut cmd_opts = command_options(&[]);
+    cmd_opts.apply_config(&config).unwrap();
+    let spec = TargetSpec::find("aarch64-linux-android").unwrap();
+    assert_eq!(cmd_opts.api_levels().level(spec), Some(26));
+
+    std::fs::write(
+        &user_path,
+        "[target.arm64-v8a]\napi-level = 23\n[target.aarch64]\napi-level = 24\n",
+    )
+    .unwrap();
+    assert!(Config::load_file(user_path.as_path()).is_err());
+    std::fs::write(&user_path, "[target.mips]\napi-level = 23\n").unwrap();
+    assert!(Config::load_file(user_path.as_path()).is_err());
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[test]
+fn invalid_config_test() {
+    let root = std::env::temp_dir().join("andrust_invalid_config_test");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    assert_eq!(Config::load(root.as_path()), Ok(None));
+
+    let path = root.join(config::CONFIG_FILE);
+    std::fs::write(&path, "[output]\njni-libs = \"jniLibs\"\n").unwrap();
+    let config = Config::load(root.as_path()).unwrap().unwrap();
+    assert_eq!(config.jni_libs(), Some(root.join("jniLibs").as_path()));
+
+    // A broken or unknown key is an error instead of an ignored file.
+    std::fs::write(&path, "[ndk]\npath = \"/opt/ndk\"\n").unwrap();
+    let err = Config::load(root.as_path()).unwrap_err();
+    assert!(err.contains(config::CONFIG_FILE));
+    std::fs::write(&path, "targets = [").unwrap();
+    assert!(Config::layered(root.as_path()).is_err());
+    let _ = std::fs::remove_dir_all(&root);
+
+    let env = Config::from_vars(|name| match name {
+        "ANDRUST_API_LEVEL" => Some("latest".to_owned()),
+        _ => None,
+    });
+    assert!(env.unwrap_err().contains("ANDRUST_API_LEVEL"));
+}
+
+#[test]
+fn user_config_test() {
+    let existing = "# my machine\n[download]\nmirrors = [\"http://mirror.local/\"]\nproxy = \"http://proxy:3128\"\n";
+    let saved = config::set_ndk_root(existing, Path::new("/opt/android-ndk-r25c")).unwrap();
+    assert!(saved.starts_with(existing));
+    let user = parse(saved.as_str()).unwrap();
+    assert_eq!(user.ndk_root(), Some(Path::new("/opt/android-ndk-r25c")));
+    assert_eq!(
+        user.mirrors(),
+        Some(&["http://mirror.local/".to_owned()][..])
+    );
+
+    assert_eq!(user.proxy(), Some("http://proxy:3128"));
+
+    // HTTPS_PROXY is the environment layer, and beats the proxy of the user config.
+    let env = Config::from_vars(|name| match name {
+        "https_proxy" => Some("http://env-proxy:8080".to_owned()),
+        _ => None,
+    })
+    .unwrap();
+    let mut cmd_opts = command_options(&[]);
+    cmd_opts
+        .apply_config(&user.clone().overridden_by(env))
+        .unwrap();
+    assert_eq!(cmd_opts.proxy(), Some("http://env-proxy:8080"));
+
+    let saved_again = config::set_ndk_root(saved.as_str(), Path::new("/opt/ndk")).unwrap();
+    assert_eq!(saved_again.matches("root").count(), 1);
+    assert!(config::set_ndk_root("ndk = 1", Path::new("/opt/ndk")).is_err());
+
+    let user = parse(
+        r#"
+        [sdk]
+        root = "/opt/android-sdk"
+
+        [download]
+        mirrors = ["https://mirror.example.com/android/repository/"]
+        "#,
+    )
+    .unwrap();
+    let mut cmd_opts = command_options(&[]);
+    cmd_opts.apply_config(&user).unwrap();
+    assert_eq!(cmd_opts.sdk_root(), Some(Path::new("/opt/android-sdk")));
+    let urls = platform::mirror_urls(
+        cmd_opts.mirrors(),
+        &["https://dl.google.com/android/repository/android-ndk-r25c-linux.zip".to_owned()],
+    );
+    assert_eq!(
+        urls,
+        vec![
+            "https://mirror.example.com/android/repository/android-ndk-r25c-linux.zip",
+            "https://dl.google.com/android/repository/android-ndk-r25c-linux.zip",
+        ]
+    );
+}
@@ -1,114 +1,500 @@
-use std::cell::{RefCell, RefMut};
+use std::fmt;
 use std::fs;
-use std::io::{stdout, Stdout, Write};
+use std::io::{self, Write};
 
-use crossterm::{cursor, QueueableCommand};
+use super::{catalog::ReleaseCatalog, progress::DownloadIndicator};
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
 use webcraft::{
-    hyper::Body, hyper::Request, hyper::Uri, Craft, CraftError, CraftResult, SaveFileObserver,
+    hyper::body::HttpBody, hyper::client::HttpConnector, hyper::header, hyper::Body, hyper::Client,
+    hyper::Request, hyper::Response, hyper::StatusCode, hyper::Uri, Craft, CraftError,
 };
 
-struct DownloadIndicator {
-    stdout: RefCell<Stdout>,
-}
-
-impl Default for DownloadIndicator {
-    fn default() -> Self {
-        let mut stdout = stdout();
-        let _ = stdout.queue(cursor::SavePosition);
-
-        DownloadIndicator {
-            stdout: RefCell::new(stdout),
-        }
-    }
-}
-
-impl SaveFileObserver for DownloadIndicator {
-    fn on_save(&self, file: &fs::File) {
-        let _ = file.metadata().map(|meta| {
-            let stdout = self.stdout.borrow_mut();
-            let mut stdout: RefMut<Stdout> = RefMut::map(stdout, |t| t);
-            let _ = stdout.write(format!("size : {}", meta.len()).as_bytes());
-            let _ = stdout.queue(cursor::RestorePosition);
-            let _ = stdout.flush();
-        });
-    }
-}
+type ProxyClient = Client<hyper_proxy::ProxyConnector<hyper_tls::HttpsConnector<HttpConnector>>>;
 
 pub struct Downloader {
     craft: Craft,
+    /// Sends the requests through a proxy instead of `craft`.
+    proxied: Option<ProxyClient>,
+    quiet: bool,
 }
 
 impl Default for Downloader {
     fn default() -> Self {
         let craft = Craft::default();
-        Downloader { craft }
+        Downloader {
+            craft,
+            proxied: None,
+            quiet: false,
+        }
     }
 }
 
 impl Downloader {
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Downloads through the HTTP proxy at `proxy`, e.g. `http://proxy.example.com:3128`.
+    ///
+    /// https urls are tunneled with `CONNECT`, so the proxy does not see their content.
+    pub fn proxy(mut self, proxy: &str) -> DownloadResult<Self> {
+        use hyper_proxy::{Intercept, Proxy, ProxyConnector};
+
+        let uri = proxy
+            .parse::<Uri>()
+            .map_err(|err| DownloadError::Proxy(format!("{} : {}", proxy, err)))?;
+        let connector = ProxyConnector::from_proxy(
+            hyper_tls::HttpsConnector::new(),
+            Proxy::new(Intercept::All, uri),
+        )
+        .map_err(|err| DownloadError::Proxy(format!("{} : {}", proxy, err)))?;
+        self.proxied = Some(Client::builder().build(connector));
+        Ok(self)
+    }
+
+    /// Tries every mirror in order and returns the url which the file was downloaded from.
+    ///
+    /// A mirror is skipped when the connection fails, it responds with an error status
+    /// or the downloaded file does not match `checksum`.
     #[tokio::main]
-    pub async fn download(&self, uri: Uri, file_name: &str) -> CraftResult<()> {
-        let req = Request::builder()
-            .method("GET")
-            .uri(uri)
+    pub async fn download_from_mirrors<'u>(
+        &self,
+        urls: &'u [String],
+        file_name: &str,
+        checksum: &Checksum,
+    ) -> DownloadResult<&'u str> {
+        if urls.is_empty() {
+            return Err(DownloadError::NoMirror);
+        }
+
+        let mut failures = Vec::new();
+        for url in urls {
+            let result = match url.parse::<Uri>() {
+                Ok(uri) => self.fetch(uri, file_name, checksum).await,
+                Err(_) => Err(DownloadError::InvalidUrl(url.to_owned())),
+            };
+
+            match result {
+                Ok(()) => {
+                    println!("downloaded from {}", url);
+                    return Ok(url.as_str());
+                }
+                Err(err) => {
+                    println!("failed to download from {} : {}", url, err);
+                    let _ = fs::remove_file(file_name);
+                    failures.push((url.to_owned(), err));
+                }
+            }
+        }
+
+        Err(DownloadError::AllMirrorsFailed(failures))
+    }
+
+    async fn fetch(&self, uri: Uri, file_name: &str, checksum: &Checksum) -> DownloadResult<()> {
+        match self.fetch_part(uri.clone(), file_name, checksum).await {
+            Err(DownloadError::ResumeRejected) => self.fetch_part(uri, file_name, checksum).await,
+            result => result,
+        }
+    }
+
+    /// Downloads into `<file_name>.part` and renames it when the body is complete.
+    ///
+    /// An existing `.part` file is resumed with a `Range` request when its metadata
+    /// has a validator, and the server decides with `If-Range` whether it is still valid.
+    /// The size and digests are computed while streaming and a mismatching file is deleted.
+    async fn fetch_part(
+        &self,
+        uri: Uri,
+        file_name: &str,
+        checksum: &Checksum,
+    ) -> DownloadResult<()> {
+        let url = uri.to_string();
+        let part_name = format!("{}.part", file_name);
+        let meta_name = format!("{}.part.json", file_name);
+
+        let resume = PartialDownload::load(meta_name.as_str())
+            .filter(|partial| partial.url == url && partial.validator().is_some())
+            .and_then(|partial| {
+                let received = fs::metadata(part_name.as_str()).ok()?.len();
+                Some((partial, received))
+            })
+            .filter(|(_, received)| *received > 0);
+
+        let mut req = Request::builder().method("GET").uri(uri);
+        if let Some((partial, received)) = resume.as_ref() {
+            println!("resuming download from {} bytes", received);
+            req = req
+                .header(header::RANGE, format!("bytes={}-", received))
+                .header(header::IF_RANGE, partial.validator().unwrap_or_default());
+        }
+        let req = req
             .body(Body::empty())
-            .map_err(|_err| CraftError::HyperConnector)?;
+            .map_err(|_err| DownloadError::Connection(CraftError::HyperConnector))?;
+
+        let response = match self.proxied.as_ref() {
+            Some(client) => client
+                .request(req)
+                .await
+                .map_err(|err| DownloadError::Proxy(err.to_string()))?,
+            None => self
+                .craft
+                .visit(req, &|response| Result::Ok(response))
+                .await
+                .map_err(DownloadError::Connection)?,
+        };
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE && resume.is_some() {
+            // The .part file is already complete or longer than the file, start over.
+            let _ = fs::remove_file(part_name.as_str());
+            let _ = fs::remove_file(meta_name.as_str());
+            return Err(DownloadError::ResumeRejected);
+        }
+        if !response.status().is_success() {
+            return Err(DownloadError::HttpStatus(response.status().as_u16()));
+        }
+
+        let mut partial = PartialDownload::from_response(url, &response);
+        let resumed_from = resume.and_then(|(previous, received)| {
+            let is_same_file = previous.validator() == partial.validator();
+            let is_resumed = response.status() == StatusCode::PARTIAL_CONTENT
+                && content_range_start(&response) == Some(received);
+            if is_same_file && is_resumed {
+                Some(received)
+            } else {
+                None
+            }
+        });
+        if response.status() == StatusCode::PARTIAL_CONTENT && resumed_from.is_none() {
+            // The partial content does not continue the .part file, start over.
+            let _ = fs::remove_file(part_name.as_str());
+            let _ = fs::remove_file(meta_name.as_str());
+            return Err(DownloadError::ResumeRejected);
+        }
+
+        let mut digest = DigestState::new(checksum);
+        if resumed_from.is_some() {
+            let mut received = fs::File::open(part_name.as_str()).map_err(DownloadError::Io)?;
+            io::copy(&mut received, &mut digest).map_err(DownloadError::Io)?;
+        }
+
+        let mut file = match resumed_from {
+            Some(_) => fs::OpenOptions::new().append(true).open(part_name.as_str()),
+            None => fs::File::create(part_name.as_str()),
+        }
+        .map_err(DownloadError::Io)?;
+        partial.received = resumed_from.unwrap_or(0);
+        partial
+            .save(meta_name.as_str())
+            .map_err(DownloadError::Io)?;
+
+        let content_length = response
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        let resumed = resumed_from.unwrap_or(0);
+        let indicator = if self.quiet {
+            None
+        } else {
+            Some(DownloadIndicator::new(
+                content_length.map(|length| resumed + length),
+                resumed,
+            ))
+        };
+        let mut body = response.into_body();
+        let mut saved_at = partial.received;
+        while let Some(chunk) = body.data().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(_err) => {
+                    let _ = partial.save(meta_name.as_str());
+                    return Err(DownloadError::Interrupted);
+                }
+            };
+            file.write_all(&chunk).map_err(DownloadError::Io)?;
+            digest.update(&chunk);
+            partial.received += chunk.len() as u64;
+            if partial.received - saved_at >= PARTIAL_SAVE_INTERVAL {
+                let _ = partial.save(meta_name.as_str());
+                saved_at = partial.received;
+            }
+            if let Some(indicator) = indicator.as_ref() {
+                indicator.report(partial.received);
+            }
+        }
+        file.flush().map_err(DownloadError::Io)?;
+        drop(file);
 
-        let body = self
-            .craft
-            .visit(req, &|response| Result::Ok(response))
-            .await?
-            .into_body();
+        if let Err(err) = digest.verify(checksum) {
+            let _ = fs::remove_file(part_name.as_str());
+            let _ = fs::remove_file(meta_name.as_str());
+            return Err(err);
+        }
 
-        let indicator = DownloadIndicator::default();
-        Craft::save_body(body, file_name, Some(Box::new(indicator))).await?;
+        fs::rename(part_name.as_str(), file_name).map_err(DownloadError::Io)?;
+        let _ = fs::remove_file(meta_name.as_str());
 
         Result::Ok(())
     }
 }
 
-pub struct BuildPlatformConfig {
-    download_url: Option<Vec<String>>,
+fn content_range_start(response: &Response<Body>) -> Option<u64> {
+    // Content-Range: bytes <start>-<end>/<total>
+    response
+        .headers()
+        .get(header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .trim_start_matches("bytes ")
+        .split('-')
+        .next()?
+        .parse()
+        .ok()
 }
 
-impl Default for BuildPlatformConfig {
-    #[cfg(target_os = "windows")]
-    fn default() -> Self {
-        let windows = vec![
-            "https://dl.google.com/android/repository/android-ndk-r21b-windows-x86_64.zip"
-                .to_owned(),
-        ];
-        BuildPlatformConfig {
-            download_url: Some(windows),
+/// Expected size and digests of a downloaded file, the digests written as hex strings.
+#[derive(Default, Clone)]
+pub struct Checksum {
+    pub size: Option<u64>,
+    pub sha1: Option<String>,
+    pub sha256: Option<String>,
+}
+
+impl Checksum {
+    pub fn has_digest(&self) -> bool {
+        self.sha1.is_some() || self.sha256.is_some()
+    }
+}
+
+struct DigestState {
+    size: u64,
+    sha1: Option<Sha1>,
+    sha256: Option<Sha256>,
+}
+
+impl DigestState {
+    fn new(checksum: &Checksum) -> Self {
+        DigestState {
+            size: 0,
+            sha1: checksum.sha1.as_ref().map(|_| Sha1::new()),
+            sha256: checksum.sha256.as_ref().map(|_| Sha256::new()),
         }
     }
 
-    #[cfg(target_os = "macos")]
-    fn default() -> Self {
-        let macos = vec![
-            "https://dl.google.com/android/repository/android-ndk-r21b-darwin-x86_64.zip"
-                .to_owned(),
-        ];
-        BuildPlatformConfig {
-            download_url: Some(macos),
+    fn update(&mut self, data: &[u8]) {
+        self.size += data.len() as u64;
+        if let Some(sha1) = self.sha1.as_mut() {
+            sha1.update(data);
+        }
+        if let Some(sha256) = self.sha256.as_mut() {
+            sha256.update(data);
         }
     }
 
-    #[cfg(target_os = "linux")]
-    fn default() -> Self {
-        let linux = vec![
-            "https://dl.google.com/android/repository/android-ndk-r21b-linux-x86_64.zip".to_owned(),
+    fn verify(self, checksum: &Checksum) -> DownloadResult<()> {
+        if let Some(expected) = checksum.size.filter(|&expected| expected != self.size) {
+            return Err(DownloadError::SizeMismatch {
+                expected,
+                actual: self.size,
+            });
+        }
+
+        let digests = vec![
+            (
+                "sha1",
+                checksum.sha1.as_ref(),
+                self.sha1.map(|sha1| format!("{:x}", sha1.finalize())),
+            ),
+            (
+                "sha256",
+                checksum.sha256.as_ref(),
+                self.sha256.map(|sha256| format!("{:x}", sha256.finalize())),
+            ),
         ];
-        BuildPlatformConfig {
-            download_url: Some(linux),
+
+        for (algorithm, expected, actual) in digests {
+            if let (Some(expected), Some(actual)) = (expected, actual) {
+                if !expected.eq_ignore_ascii_case(actual.as_str()) {
+                    return Err(DownloadError::ChecksumMismatch {
+                        algorithm,
+                        expected: expected.to_owned(),
+                        actual,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Write for DigestState {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+const PARTIAL_SAVE_INTERVAL: u64 = 8 * 1024 * 1024;
+
+/// Metadata stored next to a `.part` file so that an interrupted download can be resumed.
+#[derive(Serialize, Deserialize)]
+struct PartialDownload {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    #[serde(default)]
+    received: u64,
+}
+
+impl PartialDownload {
+    fn from_response(url: String, response: &Response<Body>) -> Self {
+        let header_value = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value: &header::HeaderValue| value.to_str().ok())
+                .map(|value| value.to_owned())
+        };
+
+        PartialDownload {
+            url,
+            etag: header_value(header::ETAG),
+            last_modified: header_value(header::LAST_MODIFIED),
+            received: 0,
+        }
+    }
+
+    fn load(meta_name: &str) -> Option<Self> {
+        let json = fs::read_to_string(meta_name).ok()?;
+        serde_json::from_str(json.as_str()).ok()
+    }
+
+    fn save(&self, meta_name: &str) -> io::Result<()> {
+        let json = serde_json::to_string(self)?;
+        fs::write(meta_name, json)
+    }
+
+    fn validator(&self) -> Option<String> {
+        self.etag.clone().or_else(|| self.last_modified.clone())
+    }
+}
+
+pub type DownloadResult<T> = Result<T, DownloadError>;
+
+#[derive(Debug)]
+pub enum DownloadError {
+    NoMirror,
+    InvalidUrl(String),
+    Connection(CraftError),
+    Proxy(String),
+    HttpStatus(u16),
+    ResumeRejected,
+    Interrupted,
+    SizeMismatch {
+        expected: u64,
+        actual: u64,
+    },
+    ChecksumMismatch {
+        algorithm: &'static str,
+        expected: String,
+        actual: String,
+    },
+    Io(io::Error),
+    AllMirrorsFailed(Vec<(String, DownloadError)>),
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DownloadError::NoMirror => write!(formatter, "NoMirror"),
+            DownloadError::InvalidUrl(url) => write!(formatter, "InvalidUrl : {}", url),
+            DownloadError::Connection(err) => write!(formatter, "Connection : {:?}", err),
+            DownloadError::Proxy(reason) => write!(formatter, "Proxy : {}", reason),
+            DownloadError::HttpStatus(status) => write!(formatter, "HttpStatus : {}", status),
+            DownloadError::ResumeRejected => write!(formatter, "ResumeRejected"),
+            DownloadError::Interrupted => write!(formatter, "Interrupted"),
+            DownloadError::SizeMismatch { expected, actual } => write!(
+                formatter,
+                "SizeMismatch : expected {} bytes, but was {} bytes",
+                expected, actual
+            ),
+            DownloadError::ChecksumMismatch {
+                algorithm,
+                expected,
+                actual,
+            } => write!(
+                formatter,
+                "ChecksumMismatch : {} expected {}, but was {}",
+                algorithm, expected, actual
+            ),
+            DownloadError::Io(err) => write!(formatter, "Io : {}", err),
+            DownloadError::AllMirrorsFailed(failures) => {
+                write!(formatter, "AllMirrorsFailed :")?;
+                for (url, err) in failures {
+                    write!(formatter, " [{} : {}]", url, err)?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
+pub struct BuildPlatformConfig {
+    version: Option<String>,
+    download_url: Option<Vec<String>>,
+    size: Option<u64>,
+    sha1: Option<String>,
+    sha256: Option<String>,
+}
+
+impl Default for BuildPlatformConfig {
+    fn default() -> Self {
+        let catalog = ReleaseCatalog::load();
+        catalog
+            .default_version()
+            .and_then(|version| BuildPlatformConfig::from_catalog(&catalog, version))
+            .unwrap_or(BuildPlatformConfig {
+                version: None,
+                download_url: None,
+                size: None,
+                sha1: None,
+                sha256: None,
+            })
+    }
+}
+
 impl BuildPlatformConfig {
-    pub fn download_url(&self) -> Option<&str> {
-        self.download_url
-            .as_ref()
-            .and_then(|vec| Some(vec[0].as_str()))
+    pub fn from_catalog(catalog: &ReleaseCatalog, version: &str) -> Option<Self> {
+        let release = catalog.release(version)?;
+        let package = release.host_package()?;
+        Some(BuildPlatformConfig {
+            version: Some(release.version().to_owned()),
+            download_url: Some(package.urls().to_vec()),
+            size: package.size(),
+            sha1: package.sha1().map(|sha1| sha1.to_owned()),
+            sha256: package.sha256().map(|sha256| sha256.to_owned()),
+        })
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    pub fn download_urls(&self) -> &[String] {
+        self.download_url.as_deref().unwrap_or(&[])
+    }
+
+    pub fn checksum(&self) -> Checksum {
+        Checksum {
+            size: self.size,
+            sha1: self.sha1.clone(),
+            sha256: self.sha256.clone(),
+        }
     }
 }
@@ -1,12 +1,254 @@
 use super::downloader;
 
+fn serve<F>(handler: F) -> String
+where
+    F: Fn(&str) -> String + Send + 'static,
+{
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request = [0u8; 1024];
+            let read = stream.read(&mut request).unwrap_or(0);
+            let request = String::from_utf8_lossy(&request[..read]).to_lowercase();
+            let _ = stream.write_all(handler(request.as_str()).as_bytes());
+        }
+    });
+    format!("http://{}/ndk.zip", addr)
+}
+
+fn serve_once(response: &'static str) -> String {
+    serve(move |_request| response.to_owned())
+}
+
+fn unused_url() -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    format!("http://{}/ndk.zip", listener.local_addr().unwrap())
+}
+
+#[test]
+fn mirror_failover_test() {
+    let file_name = std::env::temp_dir().join("andrust_mirror_failover_test.zip");
+    let file_name = file_name.to_str().unwrap();
+
+    let refused = unused_url();
+    let server_error =
+        serve_once("HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\n\r\n");
+    let not_found = serve_once("HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n");
+    let working = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhello");
+    let mirrors = vec![
+        refused,
+        "not a url".to_owned(),
+        server_error,
+        not_found,
+        working.clone(),
+    ];
+
+    let downloader = downloader::Downloader::default();
+    let mirror = downloader
+        .download_from_mirrors(&mirrors, file_name, &downloader::Checksum::default())
+        .unwrap();
+
+    assert_eq!(mirror, working.as_str());
+    assert_eq!(std::fs::read_to_string(file_name).unwrap(), "hello");
+    let _ = std::fs::remove_file(file_name);
+}
+
+#[test]
+fn all_mirrors_failed_test() {
+    let file_name = std::env::temp_dir().join("andrust_all_mirrors_failed_test.zip");
+    let file_name = file_name.to_str().unwrap();
+
+    let mirrors = vec![
+        unused_url(),
+        serve_once("HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\n\r\n"),
+    ];
+
+    let downloader = downloader::Downloader::default();
+    match downloader.download_from_mirrors(&mirrors, file_name, &downloader::Checksum::default()) {
+        Err(downloader::DownloadError::AllMirrorsFailed(failures)) => {
+            assert_eq!(failures.len(), 2);
+            match failures[1].1 {
+                downloader::DownloadError::HttpStatus(503) => (),
+                ref err => panic!("unexpected error : {}", err),
+            }
+        }
+        _ => panic!("download should fail"),
+    }
+    assert!(!std::path::Path::new(file_name).exists());
+}
+
+fn prepare_partial_download(file_name: &str, url: &str, received: &str) {
+    std::fs::write(format!("{}.part", file_name), received).unwrap();
+    std::fs::write(
+        format!("{}.part.json", file_name),
+        format!(
+            r#"{{"url":"{}","etag":"\"v1\"","last_modified":null}}"#,
+            url
+        ),
+    )
+    .unwrap();
+}
+
 #[test]
-fn downloader_test() {
+fn resume_download_test() {
+    let file_name = std::env::temp_dir().join("andrust_resume_download_test.zip");
+    let file_name = file_name.to_str().unwrap();
+
+    let url = serve(|request| {
+        if request.contains("range: bytes=5-") && request.contains("if-range: \"v1\"") {
+            "HTTP/1.1 206 Partial Content\r\netag: \"v1\"\r\ncontent-range: bytes 5-10/11\r\ncontent-length: 6\r\n\r\n world".to_owned()
+        } else {
+            "HTTP/1.1 500 Internal Server Error\r\ncontent-length: 0\r\n\r\n".to_owned()
+        }
+    });
+    prepare_partial_download(file_name, url.as_str(), "hello");
+
     let downloader = downloader::Downloader::default();
-    let _ = downloader.download(
-        "https://dl.google.com/android/repository/android-ndk-r21b-windows-x86_64.zip"
-            .parse()
-            .unwrap(),
-        "ndk.zip",
-    );
+    downloader
+        .download_from_mirrors(&[url], file_name, &downloader::Checksum::default())
+        .unwrap();
+
+    assert_eq!(std::fs::read_to_string(file_name).unwrap(), "hello world");
+    assert!(!std::path::Path::new(&format!("{}.part", file_name)).exists());
+    assert!(!std::path::Path::new(&format!("{}.part.json", file_name)).exists());
+    let _ = std::fs::remove_file(file_name);
+}
+
+#[test]
+fn restart_ignored_range_test() {
+    let file_name = std::env::temp_dir().join("andrust_restart_ignored_range_test.zip");
+    let file_name = file_name.to_str().unwrap();
+
+    let url =
+        serve_once("HTTP/1.1 200 OK\r\netag: \"v2\"\r\ncontent-length: 11\r\n\r\nhello world");
+    prepare_partial_download(file_name, url.as_str(), "stale");
+
+    let downloader = downloader::Downloader::default();
+    downloader
+        .download_from_mirrors(&[url], file_name, &downloader::Checksum::default())
+        .unwrap();
+
+    assert_eq!(std::fs::read_to_string(file_name).unwrap(), "hello world");
+    let _ = std::fs::remove_file(file_name);
+}
+
+#[test]
+fn restart_complete_part_test() {
+    let file_name = std::env::temp_dir().join("andrust_restart_complete_part_test.zip");
+    let file_name = file_name.to_str().unwrap();
+
+    let url = serve(|request| {
+        if request.contains("range: bytes=") {
+            "HTTP/1.1 416 Range Not Satisfiable\r\ncontent-range: bytes */11\r\ncontent-length: 0\r\n\r\n".to_owned()
+        } else {
+            "HTTP/1.1 200 OK\r\netag: \"v1\"\r\ncontent-length: 11\r\n\r\nhello world".to_owned()
+        }
+    });
+    // The process died after the body was complete, but before the rename.
+    prepare_partial_download(file_name, url.as_str(), "hello world");
+
+    let downloader = downloader::Downloader::default();
+    downloader
+        .download_from_mirrors(&[url], file_name, &downloader::Checksum::default())
+        .unwrap();
+
+    assert_eq!(std::fs::read_to_string(file_name).unwrap(), "hello world");
+    assert!(!std::path::Path::new(&format!("{}.part", file_name)).exists());
+    assert!(!std::path::Path::new(&format!("{}.part.json", file_name)).exists());
+    let _ = std::fs::remove_file(file_name);
+}
+
+#[test]
+fn checksum_mismatch_test() {
+    let file_name = std::env::temp_dir().join("andrust_checksum_mismatch_test.zip");
+    let file_name = file_name.to_str().unwrap();
+
+    let corrupted = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhellp");
+    let intact = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhello");
+    let mirrors = vec![corrupted, intact.clone()];
+    let checksum = downloader::Checksum {
+        size: Some(5),
+        sha1: Some("AAF4C61DDCC5E8A2DABEDE0F3B482CD9AEA9434D".to_owned()),
+        sha256: Some("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".to_owned()),
+    };
+
+    let downloader = downloader::Downloader::default();
+    let mirror = downloader
+        .download_from_mirrors(&mirrors, file_name, &checksum)
+        .unwrap();
+
+    assert_eq!(mirror, intact.as_str());
+    assert_eq!(std::fs::read_to_string(file_name).unwrap(), "hello");
+    let _ = std::fs::remove_file(file_name);
+
+    let corrupted = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhellp");
+    match downloader.download_from_mirrors(&[corrupted], file_name, &checksum) {
+        Err(downloader::DownloadError::AllMirrorsFailed(failures)) => match failures[0].1 {
+            downloader::DownloadError::ChecksumMismatch { algorithm, .. } => {
+                assert_eq!(algorithm, "sha1")
+            }
+            ref err => panic!("unexpected error : {}", err),
+        },
+        _ => panic!("corrupted download should fail"),
+    }
+    assert!(!std::path::Path::new(file_name).exists());
+    assert!(!std::path::Path::new(&format!("{}.part", file_name)).exists());
+}
+
+#[test]
+fn size_mismatch_test() {
+    let file_name = std::env::temp_dir().join("andrust_size_mismatch_test.zip");
+    let file_name = file_name.to_str().unwrap();
+
+    let truncated = serve_once("HTTP/1.1 200 OK\r\ncontent-length: 4\r\n\r\nhell");
+    let checksum = downloader::Checksum {
+        size: Some(5),
+        ..downloader::Checksum::default()
+    };
+
+    let downloader = downloader::Downloader::default();
+    match downloader.download_from_mirrors(&[truncated], file_name, &checksum) {
+        Err(downloader::DownloadError::AllMirrorsFailed(failures)) => match failures[0].1 {
+            downloader::DownloadError::SizeMismatch { expected, actual } => {
+                assert_eq!((expected, actual), (5, 4))
+            }
+            ref err => panic!("unexpected error : {}", err),
+        },
+        _ => panic!("truncated download should fail"),
+    }
+    assert!(!std::path::Path::new(file_name).exists());
+    assert!(!std::path::Path::new(&format!("{}.part", file_name)).exists());
+}
+
+#[test]
+fn proxy_test() {
+    let file_name = std::env::temp_dir().join("andrust_proxy_test.zip");
+    let file_name = file_name.to_str().unwrap();
+    let _ = std::fs::remove_file(file_name);
+
+    // The proxy gets the absolute url of the file, which is not resolved by andrust.
+    let proxy = serve(|request| {
+        if request.starts_with("get http://ndk.andrust.invalid/ndk.zip ") {
+            "HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhello".to_owned()
+        } else {
+            "HTTP/1.1 502 Bad Gateway\r\ncontent-length: 0\r\n\r\n".to_owned()
+        }
+    });
+    let proxy = proxy.trim_end_matches("/ndk.zip");
+
+    let downloader = downloader::Downloader::default().proxy(proxy).unwrap();
+    let urls = vec!["http://ndk.andrust.invalid/ndk.zip".to_owned()];
+    downloader
+        .download_from_mirrors(&urls, file_name, &downloader::Checksum::default())
+        .unwrap();
+    assert_eq!(std::fs::read_to_string(file_name).unwrap(), "hello");
+    let _ = std::fs::remove_file(file_name);
+
+    assert!(downloader::Downloader::default()
+        .proxy("not a url")
+        .is_err());
 }
@@ -0,0 +1,71 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+pub const LOCK_FILE: &str = "andrust.lock";
+
+const LOCK_HEADER: &str = "# This file is generated by andrust and pins the NDK of this project.\n";
+
+/// The NDK resolved for a project, stored in `andrust.lock` at the project root.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct NdkLock {
+    ndk: LockedNdk,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct LockedNdk {
+    version: String,
+    path: PathBuf,
+    host: String,
+}
+
+impl NdkLock {
+    pub fn new(version: String, path: PathBuf) -> Self {
+        NdkLock {
+            ndk: LockedNdk {
+                version,
+                path,
+                host: env::consts::OS.to_owned(),
+            },
+        }
+    }
+
+    pub fn load(proj_root: &Path) -> Option<Self> {
+        let lock = fs::read_to_string(proj_root.join(LOCK_FILE)).ok()?;
+        match toml::from_str(lock.as_str()) {
+            Ok(lock) => Some(lock),
+            Err(err) => {
+                println!("{} is ignored : {}", LOCK_FILE, err);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, proj_root: &Path) -> io::Result<()> {
+        let lock = toml::to_string(self).map_err(io::Error::other)?;
+        fs::write(
+            proj_root.join(LOCK_FILE),
+            format!("{}\n{}", LOCK_HEADER, lock),
+        )
+    }
+
+    pub fn version(&self) -> &str {
+        self.ndk.version.as_str()
+    }
+
+    pub fn path(&self) -> &Path {
+        self.ndk.path.as_path()
+    }
+
+    pub fn host(&self) -> &str {
+        self.ndk.host.as_str()
+    }
+
+    /// Whether the lock is written on this OS, as the path is not valid on the others.
+    pub fn is_for_this_host(&self) -> bool {
+        self.ndk.host == env::consts::OS
+    }
+}
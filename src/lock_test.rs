@@ -0,0 +1,47 @@
+use super::lock::{NdkLock, LOCK_FILE};
+
+use std::{fs, path::PathBuf};
+
+#[test]
+fn lock_roundtrip_test() {
+    let proj_root = std::env::temp_dir().join("andrust_lock_test");
+    let _ = fs::remove_dir_all(&proj_root);
+    fs::create_dir_all(&proj_root).unwrap();
+
+    assert!(NdkLock::load(proj_root.as_path()).is_none());
+
+    let lock = NdkLock::new(
+        "25.2.9519653".to_owned(),
+        PathBuf::from("/opt/android/ndk/25.2.9519653"),
+    );
+    lock.save(proj_root.as_path()).unwrap();
+
+    let content = fs::read_to_string(proj_root.join(LOCK_FILE)).unwrap();
+    assert!(content.contains("[ndk]"));
+    assert!(content.contains(r#"version = "25.2.9519653""#));
+
+    let loaded = NdkLock::load(proj_root.as_path()).unwrap();
+    assert_eq!(loaded, lock);
+    assert_eq!(loaded.host(), std::env::consts::OS);
+    assert!(loaded.is_for_this_host());
+    let _ = fs::remove_dir_all(&proj_root);
+}
+
+#[test]
+fn lock_of_other_host_test() {
+    let proj_root = std::env::temp_dir().join("andrust_lock_other_host_test");
+    let _ = fs::remove_dir_all(&proj_root);
+    fs::create_dir_all(&proj_root).unwrap();
+
+    fs::write(
+        proj_root.join(LOCK_FILE),
+        "[ndk]\nversion = \"25.2.9519653\"\npath = \"C:\\\\Android\\\\ndk\"\nhost = \"plan9\"\n",
+    )
+    .unwrap();
+
+    let loaded = NdkLock::load(proj_root.as_path()).unwrap();
+    assert_eq!(loaded.host(), "plan9");
+    assert!(!loaded.is_for_this_host());
+    assert_eq!(loaded.version(), "25.2.9519653");
+    let _ = fs::remove_dir_all(&proj_root);
+}
@@ -1,32 +1,229 @@
+mod backup;
+mod builder;
+mod catalog;
 mod command;
 mod config;
 mod downloader;
+mod lock;
+mod ndk;
 mod platform;
+mod progress;
+mod project;
+mod prompt;
+mod store;
 mod unarchiver;
+mod wizard;
 
-use command::{CommandOptions, CommandState};
+use std::path::Path;
+
+use backup::BackupStore;
+use command::{Command, CommandOptions, CommandState, NdkCommand, RestoreTarget};
+use config::Config;
 use platform::Platform;
 
+#[cfg(test)]
+mod backup_test;
+#[cfg(test)]
+mod builder_test;
+#[cfg(test)]
+mod catalog_test;
+#[cfg(test)]
+mod command_test;
+#[cfg(test)]
+mod config_test;
 #[cfg(test)]
 mod downloader_test;
 #[cfg(test)]
+mod lock_test;
+#[cfg(test)]
+mod ndk_test;
+#[cfg(test)]
+mod platform_test;
+#[cfg(test)]
+mod progress_test;
+#[cfg(test)]
+mod project_test;
+#[cfg(test)]
+mod prompt_test;
+#[cfg(test)]
+mod store_test;
+#[cfg(test)]
 mod unarchiver_test;
+#[cfg(test)]
+mod wizard_test;
 
-fn main() {
-    let cmd_opts = match CommandState::new() {
-        CommandState::Options(command) => command,
+pub fn main() {
+    let mut cmd_opts = match CommandState::new() {
+        CommandState::Options(command) => *command,
         CommandState::ExitWithPrint => return,
+        CommandState::Invalid(reason) => {
+            eprintln!("error : {}", reason);
+            eprintln!("see andrust --help");
+            std::process::exit(2);
+        }
     };
 
+    let project = project::Project::locate(cmd_opts.proj_root(), cmd_opts.manifest_path());
+    cmd_opts.set_project(&project);
+    let proj_root = project.root().to_path_buf();
+    let config = Config::layered(proj_root.as_path());
+    if let Err(reason) = config.and_then(|config| cmd_opts.apply_config(&config)) {
+        eprintln!("invalid config : {}", reason);
+        std::process::exit(1);
+    }
+
+    match cmd_opts.command() {
+        Command::Setup => (),
+        Command::Restore(target) => return restore(proj_root.as_path(), target),
+        Command::Unconfigure => {
+            return exit_on_error(
+                "failed to unconfigure",
+                platform::ConfigWriter::unconfigure(proj_root.as_path()),
+            )
+        }
+        _ => return run(platform(cmd_opts)),
+    }
+
+    if exit_on_platform_error("failed to start the wizard", wizard::should_run(&cmd_opts)) {
+        let finder = platform(cmd_opts.clone());
+        let chosen = wizard::run(&finder, &mut cmd_opts);
+        if !exit_on_platform_error("failed to finish the wizard", chosen) {
+            println!("the wizard is canceled, nothing is written");
+            return;
+        }
+    }
+
     let platform = platform(cmd_opts);
-    let ndk_path = platform.determine_ndk_root();
+    let ndk_path = platform.resolve_ndk_root();
     println!("ndk_path : {:?}", &ndk_path);
 
-    //TODO: download toolset
-    platform.setup_config(ndk_path.unwrap().as_path());
+    let ndk_path = exit_on_platform_error("failed to set up NDK", ndk_path);
+    exit_on_platform_error(
+        "failed to write cargo config",
+        platform.setup_config(ndk_path.as_path()),
+    );
     // rustup target add aarch64-linux-android armv7-linux-androideabi i686-linux-android x86_64-linux-android
 }
 
+/// Runs the commands other than `setup`, `restore` and `unconfigure`.
+fn run<P: Platform>(platform: P) {
+    let command = match platform.cmd_opts() {
+        Some(cmd_opts) => cmd_opts.command(),
+        None => return,
+    };
+
+    match command {
+        Command::Ndk(NdkCommand::Install) => {
+            let requirement = platform.cmd_opts().and_then(|opts| opts.ndk_version());
+            match platform.install_ndk(requirement) {
+                Ok(ndk_root) => println!("NDK is ready at {}", ndk_root.display()),
+                Err(err) => exit_on_platform_error("failed to install NDK", Err(err)),
+            }
+        }
+        Command::Ndk(NdkCommand::List) => P::list_ndks(),
+        Command::Ndk(NdkCommand::Remove(version)) => {
+            exit_on_error("failed to remove NDK", P::remove_ndk(version.as_str()))
+        }
+        Command::Build => {
+            let mut triples: Vec<&str> = platform
+                .targets()
+                .iter()
+                .map(|target| target.to_platform_toolset().target())
+                .collect();
+            triples.sort();
+            let cmd_opts = match platform.cmd_opts() {
+                Some(cmd_opts) => cmd_opts,
+                None => return,
+            };
+            let proj_root = platform.proj_root();
+            let target_dir = cmd_opts
+                .target_dir()
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| proj_root.join("target"));
+            let reports = builder::Builder::new(proj_root.as_path(), target_dir.as_path())
+                .manifest_path(cmd_opts.manifest_path())
+                .jni_libs(cmd_opts.jni_libs())
+                .release(cmd_opts.release())
+                .jobs(cmd_opts.jobs())
+                .cargo_args(cmd_opts.cargo_args())
+                .build(&triples);
+
+            print!("\n{}", builder::format_summary(&reports));
+            if !reports.iter().all(|report| report.is_built()) {
+                std::process::exit(1);
+            }
+        }
+        Command::Doctor => {
+            if !platform.doctor() {
+                std::process::exit(1);
+            }
+        }
+        Command::Env => exit_on_platform_error("failed to resolve toolsets", platform.print_env()),
+        Command::Clean => exit_on_error("failed to clean", P::clean()),
+        Command::Setup | Command::Restore(_) | Command::Unconfigure => (),
+    }
+}
+
+fn exit_on_error<E: std::fmt::Display>(message: &str, result: Result<(), E>) {
+    if let Err(err) = result {
+        eprintln!("{} : {}", message, err);
+        std::process::exit(1);
+    }
+}
+
+/// Exits with the exit code of the error, which tells a prompt in non-interactive mode
+/// from the other failures.
+fn exit_on_platform_error<T>(message: &str, result: Result<T, platform::PlatformError>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(err) => {
+            eprintln!("{} : {}", message, err);
+            std::process::exit(err.exit_code());
+        }
+    }
+}
+
+fn restore(proj_root: &Path, target: &RestoreTarget) {
+    let store = BackupStore::new(proj_root);
+    let backup = match target {
+        RestoreTarget::List => {
+            for backup in store.list() {
+                println!("{}  {}", backup.id(), backup.path().display());
+            }
+            return;
+        }
+        RestoreTarget::Latest => store.latest(),
+        RestoreTarget::Id(id) => store.get(id.as_str()),
+    };
+
+    let backup = match backup {
+        Some(backup) => backup,
+        None => {
+            eprintln!("no backup to restore, see andrust restore --list");
+            std::process::exit(1);
+        }
+    };
+    let config_path = platform::ConfigWriter::path_of(proj_root);
+    let restored = store.backup(config_path.as_path()).and_then(|current| {
+        if let Some(current) = current {
+            println!("the current cargo config is backed up as {}", current.id());
+        }
+        let restored = backup.restore(proj_root)?;
+        // A backup of `.cargo/config` replaces a later `.cargo/config.toml`, and vice versa.
+        if restored != config_path && config_path.exists() {
+            std::fs::remove_file(config_path.as_path())?;
+        }
+        Ok(restored)
+    });
+    match restored {
+        Ok(path) => println!("{} is restored from {}", path.display(), backup.id()),
+        Err(err) => {
+            eprintln!("failed to restore {} : {}", backup.id(), err);
+            std::process::exit(1);
+        }
+    }
+}
+
 #[cfg(target_os = "windows")]
 fn platform(cmd_opts: CommandOptions) -> impl Platform {
     platform::WinConfig::new(Some(cmd_opts))
@@ -0,0 +1,296 @@
+use std::{cmp::Ordering, fmt, fs, path::Path};
+
+/// An NDK revision such as `21.1.6352462`, which is released as `r21b`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NdkRevision {
+    major: u32,
+    minor: u32,
+    build: u32,
+    prerelease: Option<Prerelease>,
+}
+
+/// A prerelease such as `beta1` or `rc1`, a beta comes before a release candidate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Prerelease {
+    tag: String,
+    number: u32,
+}
+
+impl NdkRevision {
+    pub fn new(major: u32, minor: u32, build: u32) -> Self {
+        NdkRevision {
+            major,
+            minor,
+            build,
+            prerelease: None,
+        }
+    }
+
+    /// Parses `Pkg.Revision` (`21.1.6352462`, `22.0.6917172-beta1`) or a release
+    /// name (`r21b`, `android-ndk-r21b-linux-x86_64`).
+    pub fn parse(revision: &str) -> Option<Self> {
+        let revision = revision.trim();
+        let release_name = revision
+            .strip_prefix("android-ndk-")
+            .unwrap_or(revision)
+            .strip_prefix('r');
+
+        match release_name {
+            Some(release_name) => NdkRevision::parse_release_name(release_name),
+            None => NdkRevision::parse_pkg_revision(revision),
+        }
+    }
+
+    fn parse_pkg_revision(revision: &str) -> Option<Self> {
+        let mut split = revision.splitn(2, '-');
+        let mut numbers = split.next()?.split('.');
+        let prerelease = split.next().map(parse_prerelease);
+
+        let major = numbers.next()?.parse().ok()?;
+        let minor = match numbers.next() {
+            Some(minor) => minor.parse().ok()?,
+            None => 0,
+        };
+        let build = match numbers.next() {
+            Some(build) => build.parse().ok()?,
+            None => 0,
+        };
+
+        Some(NdkRevision {
+            major,
+            minor,
+            build,
+            prerelease,
+        })
+    }
+
+    fn parse_release_name(release_name: &str) -> Option<Self> {
+        // r21b-linux-x86_64, r22-beta1
+        let mut split = release_name.splitn(2, '-');
+        let name = split.next()?;
+        let prerelease = split
+            .next()
+            .filter(|suffix| suffix.starts_with("beta") || suffix.starts_with("rc"))
+            .map(parse_prerelease);
+
+        let digits = name.chars().take_while(|c| c.is_ascii_digit()).count();
+        let major = name[..digits].parse().ok()?;
+        let minor = match &name[digits..] {
+            "" => 0,
+            letter if letter.len() == 1 && letter.as_bytes()[0].is_ascii_lowercase() => {
+                (letter.as_bytes()[0] - b'a') as u32
+            }
+            _ => return None,
+        };
+
+        Some(NdkRevision {
+            major,
+            minor,
+            build: 0,
+            prerelease,
+        })
+    }
+
+    /// Reads `Pkg.Revision` from `source.properties` in the NDK root.
+    pub fn from_source_properties(ndk_root: &Path) -> Option<Self> {
+        let properties = fs::read_to_string(ndk_root.join("source.properties")).ok()?;
+        properties
+            .lines()
+            .filter_map(|line| {
+                let mut pair = line.splitn(2, '=');
+                Some((pair.next()?.trim(), pair.next()?.trim()))
+            })
+            .find(|(key, _)| *key == "Pkg.Revision")
+            .and_then(|(_, revision)| NdkRevision::parse(revision))
+    }
+
+    /// The revision of the NDK at `ndk_root`, from `source.properties` or the folder name.
+    pub fn detect(ndk_root: &Path) -> Option<Self> {
+        NdkRevision::from_source_properties(ndk_root).or_else(|| {
+            ndk_root
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(NdkRevision::parse)
+        })
+    }
+
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+}
+
+fn parse_prerelease(suffix: &str) -> Prerelease {
+    let digits = suffix.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    Prerelease {
+        tag: suffix[..suffix.len() - digits.len()].to_lowercase(),
+        number: digits.parse().unwrap_or(0),
+    }
+}
+
+impl Prerelease {
+    fn rank(&self) -> u32 {
+        match self.tag.as_str() {
+            "rc" => 1,
+            _ => 0,
+        }
+    }
+}
+
+impl Ord for Prerelease {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.rank(), self.number, self.tag.as_str()).cmp(&(
+            other.rank(),
+            other.number,
+            other.tag.as_str(),
+        ))
+    }
+}
+
+impl PartialOrd for Prerelease {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for NdkRevision {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.build)
+            .cmp(&(other.major, other.minor, other.build))
+            .then_with(|| match (&self.prerelease, &other.prerelease) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(lhs), Some(rhs)) => lhs.cmp(rhs),
+            })
+    }
+}
+
+impl PartialOrd for NdkRevision {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for NdkRevision {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}.{}.{}", self.major, self.minor, self.build)?;
+        if let Some(prerelease) = self.prerelease.as_ref() {
+            write!(formatter, "-{}{}", prerelease.tag, prerelease.number)?;
+        }
+        Ok(())
+    }
+}
+
+/// A requirement on the NDK revision such as `25.2.9519653`, `r25c` or `>=23, <26`.
+///
+/// Only the components written in a comparator are compared, so `25` accepts
+/// every `25.x.y` and `<26` rejects every `26.x.y`.
+#[derive(Debug, Clone)]
+pub struct NdkVersionReq {
+    requirement: String,
+    comparators: Vec<Comparator>,
+}
+
+#[derive(Debug, Clone)]
+struct Comparator {
+    op: Op,
+    revision: NdkRevision,
+    components: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+}
+
+impl NdkVersionReq {
+    pub fn parse(requirement: &str) -> Option<Self> {
+        let comparators = requirement
+            .split(',')
+            .map(|comparator| Comparator::parse(comparator.trim()))
+            .collect::<Option<Vec<_>>>()?;
+        if comparators.is_empty() {
+            return None;
+        }
+
+        Some(NdkVersionReq {
+            requirement: requirement.trim().to_owned(),
+            comparators,
+        })
+    }
+
+    pub fn exact(revision: &NdkRevision) -> Self {
+        NdkVersionReq {
+            requirement: revision.to_string(),
+            comparators: vec![Comparator {
+                op: Op::Exact,
+                revision: revision.clone(),
+                components: 3,
+            }],
+        }
+    }
+
+    pub fn matches(&self, revision: &NdkRevision) -> bool {
+        self.comparators
+            .iter()
+            .all(|comparator| comparator.matches(revision))
+    }
+}
+
+impl Comparator {
+    fn parse(comparator: &str) -> Option<Self> {
+        let (op, revision) = if let Some(revision) = comparator.strip_prefix(">=") {
+            (Op::GreaterEq, revision)
+        } else if let Some(revision) = comparator.strip_prefix("<=") {
+            (Op::LessEq, revision)
+        } else if let Some(revision) = comparator.strip_prefix('>') {
+            (Op::Greater, revision)
+        } else if let Some(revision) = comparator.strip_prefix('<') {
+            (Op::Less, revision)
+        } else if let Some(revision) = comparator.strip_prefix('=') {
+            (Op::Exact, revision)
+        } else {
+            (Op::Exact, comparator)
+        };
+        let revision = revision.trim();
+
+        let components = if revision.starts_with('r') || revision.starts_with("android-ndk-") {
+            // r25c is 25.2, and r25 is 25.0
+            2
+        } else {
+            revision.split('-').next()?.split('.').count().min(3)
+        };
+
+        Some(Comparator {
+            op,
+            revision: NdkRevision::parse(revision)?,
+            components,
+        })
+    }
+
+    fn matches(&self, revision: &NdkRevision) -> bool {
+        let truncate = |revision: &NdkRevision| {
+            let components = [revision.major, revision.minor, revision.build];
+            components[..self.components].to_vec()
+        };
+        let ordering = truncate(revision).cmp(&truncate(&self.revision));
+
+        match self.op {
+            Op::Exact => ordering == Ordering::Equal,
+            Op::Greater => ordering == Ordering::Greater,
+            Op::GreaterEq => ordering != Ordering::Less,
+            Op::Less => ordering == Ordering::Less,
+            Op::LessEq => ordering != Ordering::Greater,
+        }
+    }
+}
+
+impl fmt::Display for NdkVersionReq {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.requirement)
+    }
+}
@@ -0,0 +1,124 @@
+use super::ndk::NdkRevision;
+
+use std::fs;
+
+#[test]
+fn parse_revision_test() {
+    let r21b = NdkRevision::new(21, 1, 0);
+    assert_eq!(NdkRevision::parse("r21b"), Some(r21b.clone()));
+    assert_eq!(NdkRevision::parse("android-ndk-r21b"), Some(r21b.clone()));
+    assert_eq!(
+        NdkRevision::parse("android-ndk-r21b-linux-x86_64"),
+        Some(r21b)
+    );
+    assert_eq!(
+        NdkRevision::parse("25.2.9519653"),
+        Some(NdkRevision::new(25, 2, 9519653))
+    );
+    assert_eq!(NdkRevision::parse("r23"), Some(NdkRevision::new(23, 0, 0)));
+    assert_eq!(NdkRevision::parse("ndk-bundle"), None);
+    assert_eq!(NdkRevision::parse("r21bb"), None);
+}
+
+#[test]
+fn revision_order_test() {
+    let mut revisions = vec![
+        "9.0.1",
+        "21.1.6352462",
+        "22.0.6917172-beta1",
+        "22.0.6917172",
+        "r21",
+        "22.0.6917172-beta2",
+    ]
+    .into_iter()
+    .map(|revision| NdkRevision::parse(revision).unwrap())
+    .collect::<Vec<_>>();
+    revisions.sort();
+
+    let sorted = revisions
+        .iter()
+        .map(|revision| revision.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        sorted,
+        vec![
+            "9.0.1",
+            "21.0.0",
+            "21.1.6352462",
+            "22.0.6917172-beta1",
+            "22.0.6917172-beta2",
+            "22.0.6917172",
+        ]
+    );
+}
+
+#[test]
+fn prerelease_test() {
+    let rc1 = NdkRevision::parse("22.0.7026061-rc1").unwrap();
+    assert_eq!(rc1.to_string(), "22.0.7026061-rc1");
+    assert_eq!(
+        NdkRevision::parse("r22-beta2").unwrap().to_string(),
+        "22.0.0-beta2"
+    );
+    assert!(NdkRevision::parse("22.0.7026061-beta2").unwrap() < rc1);
+    assert!(rc1 < NdkRevision::parse("22.0.7026061").unwrap());
+}
+
+#[test]
+fn detect_revision_test() {
+    let root = std::env::temp_dir().join("andrust_detect_revision_test");
+    let _ = fs::remove_dir_all(&root);
+
+    let with_properties = root.join("ndk-bundle");
+    fs::create_dir_all(&with_properties).unwrap();
+    fs::write(
+        with_properties.join("source.properties"),
+        "Pkg.Desc = Android NDK\nPkg.Revision = 21.1.6352462\n",
+    )
+    .unwrap();
+    assert_eq!(
+        NdkRevision::detect(with_properties.as_path()),
+        Some(NdkRevision::new(21, 1, 6352462))
+    );
+
+    let folder_name_only = root.join("23.1.7779620");
+    fs::create_dir_all(&folder_name_only).unwrap();
+    assert_eq!(
+        NdkRevision::detect(folder_name_only.as_path()),
+        Some(NdkRevision::new(23, 1, 7779620))
+    );
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn version_requirement_test() {
+    use super::ndk::NdkVersionReq;
+
+    let r21b = NdkRevision::parse("21.1.6352462").unwrap();
+    let r23c = NdkRevision::parse("23.2.8568313").unwrap();
+    let r25c = NdkRevision::parse("25.2.9519653").unwrap();
+
+    let exact = NdkVersionReq::parse("25.2.9519653").unwrap();
+    assert!(exact.matches(&r25c));
+    assert!(!exact.matches(&r23c));
+
+    let release_name = NdkVersionReq::parse("r25c").unwrap();
+    assert!(release_name.matches(&r25c));
+    assert!(!release_name.matches(&NdkRevision::parse("25.1.8937393").unwrap()));
+
+    let major = NdkVersionReq::parse("23").unwrap();
+    assert!(major.matches(&r23c));
+    assert!(!major.matches(&r25c));
+
+    let at_least = NdkVersionReq::parse(">=23").unwrap();
+    assert!(!at_least.matches(&r21b));
+    assert!(at_least.matches(&r23c));
+    assert!(at_least.matches(&r25c));
+
+    let range = NdkVersionReq::parse(">=23, <25").unwrap();
+    assert!(range.matches(&r23c));
+    assert!(!range.matches(&r25c));
+
+    assert!(NdkVersionReq::exact(&r21b).matches(&r21b));
+    assert!(NdkVersionReq::parse(">=latest").is_none());
+}
@@ -1,49 +1,76 @@
+#[cfg(any(target_os = "linux", test))]
 mod linux;
+#[cfg(any(target_os = "macos", test))]
 mod mac;
+#[cfg(any(target_os = "windows", test))]
 mod win;
 
 use std::{
     self,
-    collections::HashSet,
-    fmt, format,
+    collections::{BTreeSet, HashMap, HashSet},
+    env, fmt, format, fs, io,
     path::{Path, PathBuf},
-    time::SystemTime,
 };
 
+use super::{
+    backup::BackupStore,
+    catalog::ReleaseCatalog,
+    command::{Command, CommandOptions},
+    config,
+    downloader::{BuildPlatformConfig, Downloader},
+    lock::{NdkLock, LOCK_FILE},
+    ndk::{NdkRevision, NdkVersionReq},
+    progress::format_size,
+    project,
+    prompt::{self, Prompt},
+    store::NdkStore,
+};
+
+#[cfg(any(target_os = "linux", test))]
 pub use linux::LinuxConfig;
+#[cfg(any(target_os = "macos", test))]
 pub use mac::MacConfig;
+#[cfg(any(target_os = "windows", test))]
 pub use win::WinConfig;
 
 pub trait Platform {
-    fn search_ndk_root() -> Option<PathBuf>;
+    fn search_ndk_root(platform_toolsets: &HashSet<TargetPlatform>) -> Option<PathBuf>;
+    fn ndk_install_root() -> Option<PathBuf>;
+    fn host_toolchain() -> &'static HostToolchain;
     fn determine_ndk_root(&self) -> PlatformResult<PathBuf>;
     fn targets(&self) -> &HashSet<TargetPlatform>;
-    fn setup_config(self, ndk_root: &Path);
+    fn cmd_opts(&self) -> Option<&CommandOptions>;
+    fn setup_config(self, ndk_root: &Path) -> PlatformResult<()>;
 
-    fn ask_ndk_root() -> String {
-        use std::io::{stdin, stdout, Write};
+    fn get_toolsets(layout: ToolsetLayout) -> HashSet<TargetPlatform> {
+        Self::host_toolchain().toolsets(layout)
+    }
 
-        let mut user_input = String::new();
-        println!(r#"Can't find NDK root path. System variable "NDK_TOOL_ROOT" is not set."#);
-        print!("Please enter NDK root path: ");
-        let _ = stdout().flush();
-        stdin()
-            .read_line(&mut user_input)
-            .expect("Did not enter a correct string");
-        if let Some('\n') = user_input.chars().next_back() {
-            user_input.pop();
-        }
-        if let Some('\r') = user_input.chars().next_back() {
-            user_input.pop();
+    /// The default targets, or the ones selected by `--targets` or `andrust.toml`.
+    fn selected_toolsets(cmd_opts: Option<&CommandOptions>) -> HashSet<TargetPlatform> {
+        let toolsets = Self::get_toolsets(ToolsetLayout::Binutils);
+        match cmd_opts.and_then(|opts| opts.targets()) {
+            Some(selection) => toolsets
+                .into_iter()
+                .filter(|target| selection.contains(target))
+                .collect(),
+            None => toolsets
+                .into_iter()
+                .filter(|target| target.spec().by_default)
+                .collect(),
         }
-        println!("You typed: {}", user_input);
+    }
 
-        user_input
+    fn ask_ndk_root() -> io::Result<String> {
+        println!(r#"Can't find NDK root path. System variable "NDK_TOOL_ROOT" is not set."#);
+        let user_input = prompt::ask("Please enter NDK root path: ")?;
+        println!("You typed: {}", user_input);
+        Ok(user_input)
     }
 
     fn does_toolsets_exist(ndk_root: &Path, platform_toolsets: &HashSet<TargetPlatform>) -> bool {
         let mut does_all_exist = true;
-        for target_toolset in platform_toolsets {
+        for target_toolset in Self::ndk_toolsets(platform_toolsets, ndk_root) {
             let toolsets = target_toolset.to_platform_toolset();
             let ndk_root = match ndk_root.to_str() {
                 Some(path) => path,
@@ -63,43 +90,1146 @@ pub trait Platform {
         does_all_exist
     }
 
-    fn get_latest_folder_name(root_path: &str) -> Option<String> {
+    /// The toolsets of the NDK at `ndk_root` for the targets in `platform_toolsets`.
+    ///
+    /// Targets which the NDK revision does not provide yet, such as riscv64 before r26,
+    /// are left out. `configured_toolsets` rejects them when they are selected explicitly.
+    fn ndk_toolsets(
+        platform_toolsets: &HashSet<TargetPlatform>,
+        ndk_root: &Path,
+    ) -> HashSet<TargetPlatform> {
+        let revision = assumed_revision(ndk_root);
+        Self::get_toolsets(ToolsetLayout::detect(ndk_root))
+            .into_iter()
+            .filter(|toolset| toolset.spec().is_supported_by(&revision))
+            .filter(|toolset| {
+                platform_toolsets.iter().any(|target| {
+                    target.to_platform_toolset().target() == toolset.to_platform_toolset().target()
+                })
+            })
+            .collect()
+    }
+
+    /// The toolsets to write for the NDK at `ndk_root`, linking against the requested API levels.
+    ///
+    /// A level below the minimum of the ABI is raised to it, and a level which the NDK
+    /// has no clang wrapper for is an error.
+    fn configured_toolsets(&self, ndk_root: &Path) -> PlatformResult<HashSet<TargetPlatform>> {
+        self.check_selected_targets(ndk_root)?;
+        let layout = ToolsetLayout::detect(ndk_root);
+        let api_levels = self.cmd_opts().map(|opts| opts.api_levels());
+
+        let mut toolsets = HashSet::new();
+        for target in Self::ndk_toolsets(self.targets(), ndk_root) {
+            let spec = target.spec();
+            let requested = match api_levels.and_then(|levels| levels.level(spec)) {
+                Some(level) if layout != ToolsetLayout::Standalone => level,
+                Some(level) => {
+                    println!(
+                        "API level {} is ignored for {}, a standalone toolchain has its own",
+                        level, spec.triple
+                    );
+                    toolsets.insert(target.add_ndk_root(ndk_root)?);
+                    continue;
+                }
+                None => {
+                    toolsets.insert(target.add_ndk_root(ndk_root)?);
+                    continue;
+                }
+            };
+
+            let api_level = requested.max(spec.min_api_level);
+            if api_level != requested {
+                println!(
+                    "API level {} is below the minimum of {}, {} is used",
+                    requested, spec.abi, api_level
+                );
+            }
+
+            let available = spec.available_api_levels(ndk_root);
+            if !available.contains(&api_level) {
+                let available: Vec<String> =
+                    available.iter().map(|level| level.to_string()).collect();
+                return Err(PlatformError::UnsupportedApiLevel(format!(
+                    "{} is not available for {} in {}, available levels : {}",
+                    api_level,
+                    spec.triple,
+                    ndk_root.display(),
+                    available.join(", ")
+                )));
+            }
+
+            let toolset = Self::host_toolchain().toolset(spec, layout, api_level);
+            toolsets.insert(toolset.add_ndk_root(ndk_root)?);
+        }
+        Ok(toolsets)
+    }
+
+    /// Targets selected by `--targets` or `andrust.toml` are an error when the NDK at
+    /// `ndk_root` does not provide them, unlike the default targets.
+    fn check_selected_targets(&self, ndk_root: &Path) -> PlatformResult<()> {
+        let selection = match self.cmd_opts().and_then(|opts| opts.targets()) {
+            Some(selection) => selection,
+            None => return Ok(()),
+        };
+        let revision = assumed_revision(ndk_root);
+        let unsupported = selection
+            .triples()
+            .iter()
+            .filter_map(|triple| TargetSpec::find(triple))
+            .find(|spec| !spec.is_supported_by(&revision));
+        match unsupported {
+            Some(spec) => Err(PlatformError::UnsupportedTarget(format!(
+                "{} needs NDK r{} or newer, but {} is NDK {}",
+                spec.triple,
+                spec.min_ndk_major,
+                ndk_root.display(),
+                revision
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// The NDK with the highest revision under `root_path` which has every toolset.
+    fn get_latest_ndk_root(
+        root_path: &str,
+        platform_toolsets: &HashSet<TargetPlatform>,
+    ) -> Option<PathBuf> {
         let root_path = Path::new(root_path);
         if !root_path.is_dir() {
             return None;
         }
 
-        let mut latest_folder = None;
-        for entry in std::fs::read_dir(root_path).ok()? {
-            let entry = entry.ok()?;
-            let entry_mod_time = entry.metadata().ok()?.modified().ok()?;
+        let candidates = fs::read_dir(root_path)
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir());
+        Self::select_latest_ndk_root(candidates, platform_toolsets)
+    }
 
-            let latest_folder_mod_time = latest_folder
-                .as_ref()
-                .and_then(|folder_name: &String| {
-                    Path::new(folder_name.as_str())
-                        .metadata()
-                        .ok()?
-                        .modified()
-                        .ok()
-                })
-                .unwrap_or(SystemTime::UNIX_EPOCH);
+    fn select_latest_ndk_root<I>(
+        candidates: I,
+        platform_toolsets: &HashSet<TargetPlatform>,
+    ) -> Option<PathBuf>
+    where
+        I: IntoIterator<Item = PathBuf>,
+    {
+        candidates
+            .into_iter()
+            .filter_map(|ndk_root| {
+                NdkRevision::detect(ndk_root.as_path()).map(|revision| (revision, ndk_root))
+            })
+            .filter(|(_, ndk_root)| {
+                Self::does_toolsets_exist(ndk_root.as_path(), platform_toolsets)
+            })
+            .max_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs))
+            .map(|(_, ndk_root)| ndk_root)
+    }
+
+    fn download_ndk(
+        &self,
+        download_dir: &Path,
+        config: &BuildPlatformConfig,
+    ) -> PlatformResult<PathBuf> {
+        let mirrors = self.cmd_opts().map(|opts| opts.mirrors()).unwrap_or(&[]);
+        let urls = mirror_urls(mirrors, config.download_urls());
+        let file_name = urls
+            .first()
+            .and_then(|url| url.rsplit('/').next())
+            .ok_or_else(|| PlatformError::DownloadFailed("no download url".to_owned()))?;
+        let archive_path = download_dir.join(file_name);
+        let archive_name = archive_path.to_str().ok_or(PlatformError::WrongPathName)?;
 
-            if latest_folder_mod_time < entry_mod_time {
-                latest_folder = entry
-                    .path()
-                    .as_path()
-                    .to_str()
-                    .map(|folder_name| folder_name.to_owned());
+        let checksum = config.checksum();
+        if !checksum.has_digest() {
+            if !self.is_interactive() {
+                return Err(PlatformError::InteractionRequired(
+                    Prompt::UnverifiedDownload,
+                ));
+            }
+            let question = format!(
+                "The catalog has no checksum of {}, so it can not be verified. Download it ? [y/N] ",
+                file_name
+            );
+            if !prompt::ask_yes_no(question.as_str()) {
+                return Err(PlatformError::DownloadFailed(format!(
+                    "{} is not verified, and it is not downloaded",
+                    file_name
+                )));
             }
         }
 
-        latest_folder
+        println!("downloading {}", archive_name);
+        let quiet = self.cmd_opts().map(|opts| opts.quiet()).unwrap_or(false);
+        let mut downloader = Downloader::default().quiet(quiet);
+        if let Some(proxy) = self.cmd_opts().and_then(|opts| opts.proxy()) {
+            downloader = downloader
+                .proxy(proxy)
+                .map_err(|err| PlatformError::DownloadFailed(err.to_string()))?;
+        }
+        downloader
+            .download_from_mirrors(&urls, archive_name, &checksum)
+            .map_err(|err| PlatformError::DownloadFailed(err.to_string()))?;
+
+        Ok(archive_path)
+    }
+
+    fn install_ndk(&self, requirement: Option<&NdkVersionReq>) -> PlatformResult<PathBuf> {
+        let store = NdkStore::new(Self::ndk_install_root().ok_or(PlatformError::WrongPathName)?);
+        let config = match requirement {
+            Some(requirement) => {
+                let catalog = ReleaseCatalog::load();
+                catalog
+                    .newest_release(requirement)
+                    .and_then(|release| {
+                        BuildPlatformConfig::from_catalog(&catalog, release.version())
+                    })
+                    .ok_or_else(|| {
+                        PlatformError::NdkVersionMismatch(format!(
+                            "no NDK release in the catalog matches {}",
+                            requirement
+                        ))
+                    })?
+            }
+            None => BuildPlatformConfig::default(),
+        };
+        let version = config.version().ok_or_else(|| {
+            PlatformError::DownloadFailed("no NDK release for this host".to_owned())
+        })?;
+
+        let installed_ndk = store
+            .path(version)
+            .filter(|ndk_root| Self::does_toolsets_exist(ndk_root.as_path(), self.targets()));
+        if let Some(ndk_root) = installed_ndk {
+            return Ok(ndk_root);
+        }
+
+        fs::create_dir_all(store.download_dir())
+            .map_err(|err| PlatformError::UnarchiveFailed(err.to_string()))?;
+        let archive_path = self.download_ndk(store.download_dir().as_path(), &config)?;
+
+        println!("extracting {}", archive_path.display());
+        let ndk_root = store
+            .install(version, archive_path.as_path())
+            .map_err(|err| PlatformError::UnarchiveFailed(err.to_string()))?;
+        let _ = fs::remove_file(&archive_path);
+        println!("NDK {} is installed at {}", version, ndk_root.display());
+
+        if Self::does_toolsets_exist(ndk_root.as_path(), self.targets()) {
+            Ok(ndk_root)
+        } else {
+            Err(PlatformError::ToolsetDoesNotExist)
+        }
+    }
+
+    /// Every NDK root andrust knows about without asking the user.
+    fn ndk_candidates() -> Vec<PathBuf> {
+        let mut candidates = Vec::new();
+        if let Ok(ndk_root) = env::var("NDK_TOOL_ROOT") {
+            candidates.push(PathBuf::from(ndk_root));
+        }
+        if let Ok(sdk_root) = env::var("ANDROID_HOME") {
+            candidates.extend(sdk_ndk_roots(Path::new(sdk_root.as_str())));
+        }
+        if let Some(install_root) = Self::ndk_install_root() {
+            let store = NdkStore::new(install_root);
+            candidates.extend(store.installed().iter().map(|ndk| ndk.path().to_path_buf()));
+        }
+        candidates
+    }
+
+    /// The NDKs of the Android SDK set in the config.
+    fn configured_sdk_ndk_roots(&self) -> Vec<PathBuf> {
+        self.cmd_opts()
+            .and_then(|opts| opts.sdk_root())
+            .map(sdk_ndk_roots)
+            .unwrap_or_default()
+    }
+
+    /// Asks the NDK root, and offers to save it to the user config when it has every toolset.
+    ///
+    /// Nothing is asked in non-interactive mode, or when stdin is closed, and the NDK
+    /// is downloaded instead as when the entered NDK has no toolsets.
+    fn ask_verified_ndk_root(&self) -> PlatformResult<Option<PathBuf>> {
+        if !self.is_interactive() {
+            return Ok(None);
+        }
+        let ndk_root = match Self::ask_ndk_root() {
+            Ok(ndk_root) => PathBuf::from(ndk_root),
+            Err(_) => return Ok(None),
+        };
+        if !Self::does_toolsets_exist(ndk_root.as_path(), self.targets()) {
+            return Ok(None);
+        }
+
+        if let Some(config_path) = config::user_config_path() {
+            let question = format!("Save it to {} ? [y/N] ", config_path.display());
+            if prompt::ask_yes_no(question.as_str()) {
+                match config::save_user_ndk_root(ndk_root.as_path()) {
+                    Ok(path) => println!("NDK root is saved to {}", path.display()),
+                    Err(err) => println!("failed to save NDK root : {}", err),
+                }
+            }
+        }
+        Ok(Some(ndk_root))
+    }
+
+    /// Whether andrust may ask on stdin, see `prompt::detect_non_interactive`.
+    fn is_interactive(&self) -> bool {
+        self.cmd_opts()
+            .map(|opts| opts.interactive())
+            .unwrap_or_else(|| !prompt::detect_non_interactive())
+    }
+
+    /// `--dry-run`, `--diff`, `doctor` and `env` only inspect the NDK and the cargo config,
+    /// without downloading or writing.
+    fn is_preview(&self) -> bool {
+        self.cmd_opts()
+            .map(|opts| {
+                opts.dry_run()
+                    || opts.diff()
+                    || [Command::Doctor, Command::Env].contains(opts.command())
+            })
+            .unwrap_or(false)
+    }
+
+    /// The writer of `toolsets` with the linker arguments of the config.
+    fn config_writer<'a>(&'a self, toolsets: &'a HashSet<TargetPlatform>) -> ConfigWriter<'a> {
+        let writer = ConfigWriter::new(toolsets);
+        match self.cmd_opts() {
+            Some(cmd_opts) => writer.with_link_args(cmd_opts.link_args()),
+            None => writer,
+        }
+    }
+
+    /// Writes `toolsets` to the cargo config, or shows them with `--dry-run` and `--diff`.
+    ///
+    /// After the wizard, the changes are shown and written when the user confirms them.
+    fn write_config(
+        &self,
+        ndk_root: &Path,
+        toolsets: &HashSet<TargetPlatform>,
+    ) -> PlatformResult<()> {
+        let writer = self.config_writer(toolsets);
+        let proj_root = self.proj_root();
+        let cmd_opts = match self.cmd_opts() {
+            Some(cmd_opts) if self.is_preview() || cmd_opts.confirm_write() => cmd_opts,
+            _ => return writer.write(proj_root.as_path()),
+        };
+
+        let path = writer.path(proj_root.as_path());
+        let existing = ConfigWriter::read(path.as_path())?;
+        let (content, _) = writer.merge(existing.as_str())?;
+        if !self.is_preview() {
+            if existing == content {
+                println!("{} is up to date", path.display());
+                return Ok(());
+            }
+            print_config_diff(path.as_path(), existing.as_str(), content.as_str());
+            let question = format!("Write {} ? [y/N] ", path.display());
+            if prompt::ask_yes_no(question.as_str()) {
+                return writer.write(proj_root.as_path());
+            }
+            println!("{} is not written", path.display());
+            return Ok(());
+        }
+
+        if cmd_opts.dry_run() {
+            let mut triples: Vec<&str> = toolsets
+                .iter()
+                .map(|target| target.to_platform_toolset().target())
+                .collect();
+            triples.sort();
+            println!("NDK root : {}", ndk_root.display());
+            println!("targets : {}", triples.join(", "));
+            println!("{} :\n{}", path.display(), content);
+        }
+        if cmd_opts.diff() && existing == content {
+            println!("{} is up to date", path.display());
+        } else if cmd_opts.diff() {
+            print_config_diff(path.as_path(), existing.as_str(), content.as_str());
+        }
+        Ok(())
     }
 
-    fn download_ndk() {}
+    fn proj_root(&self) -> PathBuf {
+        self.cmd_opts()
+            .and_then(|opts| opts.proj_root())
+            .unwrap_or_else(|| Path::new("."))
+            .to_path_buf()
+    }
+
+    /// Determines the NDK root and records it in `andrust.lock`.
+    ///
+    /// The NDK has to match `--ndk-version`, or the version in `andrust.lock` when the
+    /// option is not given. When no local NDK matches, the newest matching release
+    /// in the catalog is installed. The path in a lock written on another OS is not used.
+    fn resolve_ndk_root(&self) -> PlatformResult<PathBuf> {
+        let proj_root = self.proj_root();
+        let lock = NdkLock::load(proj_root.as_path());
+        let requirement = self
+            .cmd_opts()
+            .and_then(|opts| opts.ndk_version())
+            .cloned()
+            .or_else(|| {
+                lock.as_ref()
+                    .and_then(|lock| NdkRevision::parse(lock.version()))
+                    .map(|revision| NdkVersionReq::exact(&revision))
+            });
+
+        let ndk_root = match requirement.as_ref() {
+            None => match self.determine_ndk_root() {
+                Err(PlatformError::ToolsetDoesNotExist) if self.is_preview() => {
+                    Err(PlatformError::NotInstalled(format!(
+                        "NDK is not found, {}",
+                        PREVIEW_DOES_NOT_DOWNLOAD
+                    )))
+                }
+                Err(PlatformError::ToolsetDoesNotExist) => {
+                    println!("NDK is not found, it is being downloaded and installed.");
+                    self.install_ndk(None)
+                }
+                result => result,
+            }?,
+            Some(requirement) => {
+                let mut candidates = Vec::new();
+                match lock.as_ref() {
+                    Some(lock) if lock.is_for_this_host() => {
+                        candidates.push(lock.path().to_path_buf())
+                    }
+                    Some(lock) => println!(
+                        "{} is written on {}, the NDK is searched on this host",
+                        LOCK_FILE,
+                        lock.host()
+                    ),
+                    None => (),
+                }
+                candidates.extend(
+                    self.cmd_opts()
+                        .and_then(|opts| opts.ndk_root())
+                        .map(|ndk_root| ndk_root.to_path_buf()),
+                );
+                candidates.extend(Self::search_ndk_root(self.targets()));
+                candidates.extend(self.configured_sdk_ndk_roots());
+                candidates.extend(Self::ndk_candidates());
+
+                let matching = candidates.into_iter().filter(|ndk_root| {
+                    NdkRevision::detect(ndk_root.as_path())
+                        .map(|revision| requirement.matches(&revision))
+                        .unwrap_or(false)
+                });
+                match Self::select_latest_ndk_root(matching, self.targets()) {
+                    Some(ndk_root) => ndk_root,
+                    None if self.is_preview() => {
+                        return Err(PlatformError::NotInstalled(format!(
+                            "NDK {} is not found, {}",
+                            requirement, PREVIEW_DOES_NOT_DOWNLOAD
+                        )))
+                    }
+                    None => {
+                        println!(
+                            "NDK {} is not found, it is being downloaded and installed.",
+                            requirement
+                        );
+                        self.install_ndk(Some(requirement))?
+                    }
+                }
+            }
+        };
+
+        match NdkRevision::detect(ndk_root.as_path()) {
+            Some(revision) => {
+                let resolved = NdkLock::new(revision.to_string(), ndk_root.clone());
+                if lock.as_ref() != Some(&resolved) && self.is_preview() {
+                    println!("NDK {} would be recorded in {}", revision, LOCK_FILE);
+                } else if lock.as_ref() != Some(&resolved) {
+                    match resolved.save(proj_root.as_path()) {
+                        Ok(()) => println!("NDK {} is recorded in {}", revision, LOCK_FILE),
+                        Err(err) => println!("failed to write {} : {}", LOCK_FILE, err),
+                    }
+                }
+            }
+            None => println!(
+                "the revision of {} is unknown, {} is not updated",
+                ndk_root.display(),
+                LOCK_FILE
+            ),
+        }
+
+        Ok(ndk_root)
+    }
+
+    /// Prints the NDKs found on this machine, the newest first.
+    fn list_ndks() {
+        let installed: Vec<PathBuf> = Self::ndk_install_root()
+            .map(|install_root| {
+                NdkStore::new(install_root)
+                    .installed()
+                    .iter()
+                    .map(|ndk| ndk.path().to_path_buf())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut ndks: Vec<(NdkRevision, PathBuf)> = Self::ndk_candidates()
+            .into_iter()
+            .filter_map(|ndk_root| {
+                NdkRevision::detect(ndk_root.as_path()).map(|revision| (revision, ndk_root))
+            })
+            .collect();
+        ndks.sort_by(|(lhs, _), (rhs, _)| rhs.cmp(lhs));
+        ndks.dedup_by(|(_, lhs), (_, rhs)| lhs == rhs);
+
+        if ndks.is_empty() {
+            println!("no NDK is found, see andrust ndk install");
+        }
+        for (revision, ndk_root) in ndks {
+            let source = if installed.contains(&ndk_root) {
+                "installed by andrust"
+            } else {
+                "found"
+            };
+            println!(
+                "{:<20}{:<24}{}",
+                revision.to_string(),
+                source,
+                ndk_root.display()
+            );
+        }
+    }
+
+    /// Removes the NDK installed by andrust whose version or revision is `version`.
+    fn remove_ndk(version: &str) -> PlatformResult<()> {
+        let store = NdkStore::new(Self::ndk_install_root().ok_or(PlatformError::WrongPathName)?);
+        let requirement =
+            NdkRevision::parse(version).map(|revision| NdkVersionReq::exact(&revision));
+        let installed = store.installed().into_iter().find(|ndk| {
+            ndk.version() == version
+                || NdkRevision::detect(ndk.path())
+                    .zip(requirement.as_ref())
+                    .map(|(revision, requirement)| requirement.matches(&revision))
+                    .unwrap_or(false)
+        });
+
+        let installed = installed.ok_or_else(|| {
+            PlatformError::NotInstalled(format!("NDK {} is not installed by andrust", version))
+        })?;
+        store
+            .remove(installed.version())
+            .map_err(|err| PlatformError::UnarchiveFailed(err.to_string()))?;
+        println!(
+            "NDK {} at {} is removed",
+            installed.version(),
+            installed.path().display()
+        );
+        Ok(())
+    }
+
+    /// Removes the downloaded archives and partial downloads of the NDK store.
+    fn clean() -> PlatformResult<()> {
+        let store = NdkStore::new(Self::ndk_install_root().ok_or(PlatformError::WrongPathName)?);
+        let download_dir = store.download_dir();
+        if !download_dir.exists() {
+            println!("nothing to clean");
+            return Ok(());
+        }
+
+        let size: u64 = fs::read_dir(&download_dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter_map(|entry| entry.metadata().ok())
+                    .map(|metadata| metadata.len())
+                    .sum()
+            })
+            .unwrap_or(0);
+        fs::remove_dir_all(&download_dir)
+            .map_err(|err| PlatformError::UnarchiveFailed(err.to_string()))?;
+        println!(
+            "{} is removed, {} freed",
+            download_dir.display(),
+            format_size(size as f64)
+        );
+        Ok(())
+    }
+
+    /// Checks cargo, the rustup targets, the NDK and the cargo config, and tells how to fix them.
+    fn doctor(&self) -> bool {
+        let mut healthy = true;
+        let mut report = |ok: bool, check: String, hint: Option<String>| {
+            println!("[{}] {}", if ok { " ok " } else { "fail" }, check);
+            if let (false, Some(hint)) = (ok, hint) {
+                println!("       {}", hint);
+            }
+            healthy = healthy && ok;
+        };
+
+        let cargo_version = project::cargo_version();
+        report(
+            cargo_version.is_some(),
+            match cargo_version {
+                Some((major, minor)) => format!("cargo {}.{}", major, minor),
+                None => "cargo".to_owned(),
+            },
+            Some("install rust from https://rustup.rs".to_owned()),
+        );
+
+        let mut triples: Vec<&str> = self
+            .targets()
+            .iter()
+            .map(|target| target.to_platform_toolset().target())
+            .collect();
+        triples.sort();
+        let installed_targets = project::rustup_targets();
+        let missing: Vec<&str> = triples
+            .iter()
+            .copied()
+            .filter(|triple| {
+                !installed_targets
+                    .iter()
+                    .any(|installed| installed == triple)
+            })
+            .collect();
+        report(
+            missing.is_empty(),
+            format!("rustup targets {}", triples.join(", ")),
+            Some(format!("rustup target add {}", missing.join(" "))),
+        );
+
+        let ndk_root = match self.resolve_ndk_root() {
+            Ok(ndk_root) => ndk_root,
+            Err(err) => {
+                report(
+                    false,
+                    format!("NDK : {}", err),
+                    Some("andrust ndk install".to_owned()),
+                );
+                return false;
+            }
+        };
+        report(true, format!("NDK {}", ndk_root.display()), None);
+
+        let toolsets = match self.configured_toolsets(ndk_root.as_path()) {
+            Ok(toolsets) => toolsets,
+            Err(err) => {
+                report(false, format!("toolsets : {}", err), None);
+                return false;
+            }
+        };
+        let all_exist = toolsets.iter().all(|target| {
+            let toolset = target.to_platform_toolset();
+            Path::new(toolset.ar()).exists() && Path::new(toolset.linker()).exists()
+        });
+        report(
+            all_exist,
+            "toolsets of the targets".to_owned(),
+            Some("reinstall the NDK or set --ndk".to_owned()),
+        );
+
+        let path = ConfigWriter::path_of(self.proj_root().as_path());
+        let up_to_date = ConfigWriter::read(path.as_path())
+            .and_then(|existing| self.config_writer(&toolsets).merge(existing.as_str()))
+            .map(|(_, changes)| {
+                changes
+                    .iter()
+                    .all(|change| matches!(change, ConfigChange::Unchanged(_)))
+            })
+            .unwrap_or(false);
+        report(
+            up_to_date,
+            format!("cargo config {}", path.display()),
+            Some("andrust setup".to_owned()),
+        );
+
+        healthy
+    }
+
+    /// Prints the environment variables which point cargo and the `cc` crate to the toolsets.
+    fn print_env(&self) -> PlatformResult<()> {
+        let ndk_root = self.resolve_ndk_root()?;
+        let toolsets = self.configured_toolsets(ndk_root.as_path())?;
+        for (name, value) in toolset_env(ndk_root.as_path(), &toolsets) {
+            if cfg!(target_os = "windows") {
+                println!("set {}={}", name, value);
+            } else {
+                println!("export {}=\"{}\"", name, value);
+            }
+        }
+        Ok(())
+    }
+
+    fn installed_ndk_root(platform_toolsets: &HashSet<TargetPlatform>) -> Option<PathBuf> {
+        let store = NdkStore::new(Self::ndk_install_root()?);
+        let installed = store
+            .installed()
+            .into_iter()
+            .map(|ndk| ndk.path().to_path_buf());
+        Self::select_latest_ndk_root(installed, platform_toolsets)
+    }
+}
+
+/// Why a missing NDK is an error in `--dry-run` and `--diff`.
+const PREVIEW_DOES_NOT_DOWNLOAD: &str = "--dry-run and --diff do not download it";
+
+/// Prints the changes from `existing` to `content` of the cargo config at `path` as a unified diff.
+fn print_config_diff(path: &Path, existing: &str, content: &str) {
+    let name = path.display().to_string();
+    let diff = similar::TextDiff::from_lines(existing, content);
+    print!(
+        "{}",
+        diff.unified_diff().header(name.as_str(), name.as_str())
+    );
+}
+
+/// `ndk-bundle` and the side by side NDKs in `ndk/<version>` of the Android SDK at `sdk_root`.
+pub fn sdk_ndk_roots(sdk_root: &Path) -> Vec<PathBuf> {
+    let mut ndk_roots = vec![sdk_root.join("ndk-bundle")];
+    if let Ok(entries) = fs::read_dir(sdk_root.join("ndk")) {
+        ndk_roots.extend(
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path()),
+        );
+    }
+    ndk_roots
 }
 
+/// The file of each of `urls` on each of `mirrors`, then `urls` themselves.
+pub fn mirror_urls(mirrors: &[String], urls: &[String]) -> Vec<String> {
+    let mut mirror_urls: Vec<String> = mirrors
+        .iter()
+        .flat_map(|mirror| {
+            urls.iter()
+                .filter_map(|url| url.rsplit('/').next())
+                .map(move |file_name| format!("{}/{}", mirror.trim_end_matches('/'), file_name))
+        })
+        .collect();
+    for url in urls {
+        if !mirror_urls.contains(url) {
+            mirror_urls.push(url.to_owned());
+        }
+    }
+    mirror_urls
+}
+
+/// `ANDROID_NDK_HOME`, and the cargo and `cc` crate variables of the linker and `ar` of each target.
+pub fn toolset_env(ndk_root: &Path, toolsets: &HashSet<TargetPlatform>) -> Vec<(String, String)> {
+    let mut toolsets: Vec<&PlatformToolset> = toolsets
+        .iter()
+        .map(|target| target.to_platform_toolset())
+        .collect();
+    toolsets.sort_by_key(|toolset| toolset.target());
+
+    let mut env = vec![(
+        "ANDROID_NDK_HOME".to_owned(),
+        ndk_root.display().to_string(),
+    )];
+    for toolset in toolsets {
+        let cargo_target = toolset.target().to_uppercase().replace('-', "_");
+        let cc_target = toolset.target().replace('-', "_");
+        env.push((
+            format!("CARGO_TARGET_{}_LINKER", cargo_target),
+            toolset.linker().to_owned(),
+        ));
+        env.push((
+            format!("CARGO_TARGET_{}_AR", cargo_target),
+            toolset.ar().to_owned(),
+        ));
+        env.push((format!("CC_{}", cc_target), toolset.linker().to_owned()));
+        env.push((format!("AR_{}", cc_target), toolset.ar().to_owned()));
+    }
+    env
+}
+
+/// Where the NDK keeps `ar` and the clang linker, which changed over NDK revisions.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ToolsetLayout {
+    /// r18 and older, toolchains made by `make_standalone_toolchain.py` at `<ndk>/<arch>`.
+    Standalone,
+    /// r19 to r22, clang wrappers and GNU binutils in `toolchains/llvm/prebuilt`.
+    Binutils,
+    /// r23 and newer, GNU binutils are removed in favor of `llvm-ar`.
+    Llvm,
+}
+
+impl ToolsetLayout {
+    pub fn for_revision(revision: &NdkRevision) -> Self {
+        match revision.major() {
+            0..=18 => ToolsetLayout::Standalone,
+            19..=22 => ToolsetLayout::Binutils,
+            _ => ToolsetLayout::Llvm,
+        }
+    }
+
+    /// Reads the revision from `source.properties` or the folder name of `ndk_root`.
+    /// An NDK of unknown revision is assumed to have the r19 layout.
+    pub fn detect(ndk_root: &Path) -> Self {
+        ToolsetLayout::for_revision(&assumed_revision(ndk_root))
+    }
+}
+
+/// The revision of the NDK at `ndk_root`, which is assumed to be r19 when it is unknown.
+fn assumed_revision(ndk_root: &Path) -> NdkRevision {
+    NdkRevision::detect(ndk_root).unwrap_or_else(|| NdkRevision::new(19, 0, 0))
+}
+
+/// How the NDK prebuilt toolchain of a host is laid out.
+pub struct HostToolchain {
+    /// Directory name under `toolchains/llvm/prebuilt`.
+    pub prebuilt_dir: &'static str,
+    pub exe_suffix: &'static str,
+    /// The clang wrappers are `.cmd` scripts on Windows.
+    pub linker_suffix: &'static str,
+}
+
+#[cfg(any(target_os = "linux", test))]
+pub static LINUX_X86_64: HostToolchain = HostToolchain {
+    prebuilt_dir: "linux-x86_64",
+    exe_suffix: "",
+    linker_suffix: "",
+};
+
+#[cfg(any(target_os = "macos", test))]
+pub static DARWIN_X86_64: HostToolchain = HostToolchain {
+    prebuilt_dir: "darwin-x86_64",
+    exe_suffix: "",
+    linker_suffix: "",
+};
+
+#[cfg(any(target_os = "windows", test))]
+pub static WINDOWS_X86_64: HostToolchain = HostToolchain {
+    prebuilt_dir: "windows-x86_64",
+    exe_suffix: ".exe",
+    linker_suffix: ".cmd",
+};
+
+#[cfg(test)]
+pub static HOST_TOOLCHAINS: [&HostToolchain; 3] = [&LINUX_X86_64, &DARWIN_X86_64, &WINDOWS_X86_64];
+
+impl HostToolchain {
+    /// Every target's toolset of this host, relative to the NDK root.
+    pub fn toolsets(&self, layout: ToolsetLayout) -> HashSet<TargetPlatform> {
+        TARGET_SPECS
+            .iter()
+            .map(|spec| self.toolset(spec, layout, spec.default_api_level(layout)))
+            .collect()
+    }
+
+    /// The toolset of `spec` linking against `api_level`, relative to the NDK root.
+    ///
+    /// Standalone toolchains have the API level baked in, so `api_level` is unused for them.
+    pub fn toolset(
+        &self,
+        spec: &'static TargetSpec,
+        layout: ToolsetLayout,
+        api_level: u32,
+    ) -> TargetPlatform {
+        let prebuilt_bin = format!("toolchains/llvm/prebuilt/{}/bin", self.prebuilt_dir);
+        let (exe_suffix, linker_suffix) = (self.exe_suffix, self.linker_suffix);
+
+        let (ar, linker) = match layout {
+            ToolsetLayout::Standalone => (
+                format!(
+                    "{}/bin/{}-ar{}",
+                    spec.standalone_dir, spec.binutils_prefix, exe_suffix
+                ),
+                format!(
+                    "{}/bin/{}-clang{}",
+                    spec.standalone_dir, spec.binutils_prefix, linker_suffix
+                ),
+            ),
+            ToolsetLayout::Binutils => (
+                format!("{}/{}-ar{}", prebuilt_bin, spec.binutils_prefix, exe_suffix),
+                format!(
+                    "{}/{}{}-clang{}",
+                    prebuilt_bin, spec.clang_prefix, api_level, linker_suffix
+                ),
+            ),
+            ToolsetLayout::Llvm => (
+                format!("{}/llvm-ar{}", prebuilt_bin, exe_suffix),
+                format!(
+                    "{}/{}{}-clang{}",
+                    prebuilt_bin, spec.clang_prefix, api_level, linker_suffix
+                ),
+            ),
+        };
+        (spec.variant)(PlatformToolset::new(spec.triple, ar, linker))
+    }
+}
+
+/// How an Android target is named and which NDK provides it.
+pub struct TargetSpec {
+    /// Rust target triple.
+    pub triple: &'static str,
+    /// Android ABI name used for `jniLibs` directories.
+    pub abi: &'static str,
+    /// Prefix of the GNU binutils, e.g. `arm-linux-androideabi-ar`.
+    pub binutils_prefix: &'static str,
+    /// Prefix of the clang wrappers, e.g. `armv7a-linux-androideabi21-clang`.
+    pub clang_prefix: &'static str,
+    /// Directory of the standalone toolchain in the docs, e.g. `<ndk>/arm64`.
+    pub standalone_dir: &'static str,
+    pub min_api_level: u32,
+    /// The first NDK major revision which has the target.
+    pub min_ndk_major: u32,
+    /// Whether the target is set up without `--targets`. riscv64 is a tier-3 Rust target,
+    /// which rustup can not install.
+    pub by_default: bool,
+    variant: fn(PlatformToolset) -> TargetPlatform,
+}
+
+impl TargetSpec {
+    /// r24 and newer do not have API 16 wrappers, 21 is provided by every r23+ NDK.
+    fn default_api_level(&self, layout: ToolsetLayout) -> u32 {
+        match layout {
+            ToolsetLayout::Llvm => self.min_api_level.max(21),
+            _ => self.min_api_level,
+        }
+    }
+
+    pub fn is_supported_by(&self, revision: &NdkRevision) -> bool {
+        self.min_ndk_major <= revision.major()
+    }
+
+    /// Whether `name` is the triple, the ABI name or the architecture of this target.
+    pub fn is_named(&self, name: &str) -> bool {
+        self.triple == name || self.abi == name || self.triple.split('-').next() == Some(name)
+    }
+
+    pub fn find(name: &str) -> Option<&'static TargetSpec> {
+        TARGET_SPECS.iter().find(|spec| spec.is_named(name))
+    }
+
+    /// The API levels of the clang wrappers in `toolchains/llvm/prebuilt/*/bin`.
+    pub fn available_api_levels(&self, ndk_root: &Path) -> BTreeSet<u32> {
+        let prebuilt = ndk_root.join("toolchains/llvm/prebuilt");
+        let hosts = match fs::read_dir(prebuilt) {
+            Ok(hosts) => hosts,
+            Err(_) => return BTreeSet::new(),
+        };
+
+        hosts
+            .filter_map(|host| host.ok())
+            .filter_map(|host| fs::read_dir(host.path().join("bin")).ok())
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let file_name = entry.file_name();
+                let file_name = file_name.to_str()?;
+                let rest = file_name.strip_prefix(self.clang_prefix)?;
+                let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+                if digits == 0 || !rest[digits..].starts_with("-clang") {
+                    return None;
+                }
+                rest[..digits].parse().ok()
+            })
+            .collect()
+    }
+}
+
+/// Targets chosen by `--targets` or `targets` in `andrust.toml`, e.g. `aarch64,armeabi-v7a`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TargetSelection {
+    triples: Vec<&'static str>,
+}
+
+impl TargetSelection {
+    pub fn from_names<I, S>(names: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut triples = Vec::new();
+        for name in names {
+            let name = name.as_ref().trim();
+            if name.is_empty() {
+                continue;
+            }
+            let spec = TargetSpec::find(name).ok_or_else(|| format!("unknown target {}", name))?;
+            if !triples.contains(&spec.triple) {
+                triples.push(spec.triple);
+            }
+        }
+
+        if triples.is_empty() {
+            Err("no target is selected".to_owned())
+        } else {
+            Ok(TargetSelection { triples })
+        }
+    }
+
+    pub fn triples(&self) -> &[&'static str] {
+        self.triples.as_slice()
+    }
+
+    pub fn contains(&self, target: &TargetPlatform) -> bool {
+        self.triples
+            .contains(&target.to_platform_toolset().target())
+    }
+}
+
+/// API levels requested by `--api-level`, e.g. `24` or `24,arm64-v8a=26`.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct ApiLevels {
+    default: Option<u32>,
+    targets: HashMap<&'static str, u32>,
+}
+
+impl ApiLevels {
+    pub fn parse(levels: &str) -> Result<Self, String> {
+        let mut api_levels = ApiLevels::default();
+        for level in levels
+            .split(',')
+            .map(str::trim)
+            .filter(|level| !level.is_empty())
+        {
+            match level.split_once('=') {
+                Some((name, level)) => {
+                    let spec = TargetSpec::find(name.trim())
+                        .ok_or_else(|| format!("unknown target {}", name.trim()))?;
+                    api_levels
+                        .targets
+                        .insert(spec.triple, ApiLevels::parse_level(level)?);
+                }
+                None => api_levels.default = Some(ApiLevels::parse_level(level)?),
+            }
+        }
+        Ok(api_levels)
+    }
+
+    fn parse_level(level: &str) -> Result<u32, String> {
+        level
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid API level {}", level.trim()))
+    }
+
+    /// The requested API level of `spec`, a per target level overrides the global one.
+    pub fn level(&self, spec: &TargetSpec) -> Option<u32> {
+        self.targets.get(spec.triple).copied().or(self.default)
+    }
+
+    pub fn set_default(&mut self, level: u32) {
+        self.default = Some(level);
+    }
+
+    /// Sets the API level of the target `name`, a triple, ABI or architecture.
+    pub fn set(&mut self, name: &str, level: u32) -> Result<(), String> {
+        let spec = TargetSpec::find(name).ok_or_else(|| format!("unknown target {}", name))?;
+        self.targets.insert(spec.triple, level);
+        Ok(())
+    }
+
+    /// Fills the levels which are not set from `lower`, a layer of less precedence.
+    ///
+    /// A global level applies to every target, so it hides the levels of `lower`.
+    pub fn fill_from(&mut self, lower: ApiLevels) {
+        if self.default.is_some() {
+            return;
+        }
+        self.default = lower.default;
+        for (triple, level) in lower.targets {
+            self.targets.entry(triple).or_insert(level);
+        }
+    }
+}
+
+/// Extra arguments of the linker, e.g. `link-args = ["-Wl,-z,max-page-size=16384"]`.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct LinkArgs {
+    common: Vec<String>,
+    targets: HashMap<&'static str, Vec<String>>,
+}
+
+impl LinkArgs {
+    pub fn set_common(&mut self, args: &[String]) {
+        self.common = args.to_vec();
+    }
+
+    /// Sets the arguments of the target `name`, a triple, ABI or architecture.
+    pub fn set(&mut self, name: &str, args: &[String]) -> Result<(), String> {
+        let spec = TargetSpec::find(name).ok_or_else(|| format!("unknown target {}", name))?;
+        self.targets.insert(spec.triple, args.to_vec());
+        Ok(())
+    }
+
+    /// The common arguments, then the arguments of `spec`.
+    pub fn for_target(&self, spec: &TargetSpec) -> Vec<&str> {
+        self.common
+            .iter()
+            .chain(self.targets.get(spec.triple).into_iter().flatten())
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// `rustflags` of the cargo config which pass the arguments of `spec` to the linker.
+    pub fn rustflags(&self, spec: &TargetSpec) -> Vec<String> {
+        self.for_target(spec)
+            .into_iter()
+            .flat_map(|arg| vec!["-C".to_owned(), format!("link-arg={}", arg)])
+            .collect()
+    }
+}
+
+pub static TARGET_SPECS: [TargetSpec; 5] = [
+    TargetSpec {
+        triple: "aarch64-linux-android",
+        abi: "arm64-v8a",
+        binutils_prefix: "aarch64-linux-android",
+        clang_prefix: "aarch64-linux-android",
+        standalone_dir: "arm64",
+        min_api_level: 21,
+        min_ndk_major: 0,
+        by_default: true,
+        variant: TargetPlatform::Aarch64,
+    },
+    TargetSpec {
+        triple: "armv7-linux-androideabi",
+        abi: "armeabi-v7a",
+        binutils_prefix: "arm-linux-androideabi",
+        clang_prefix: "armv7a-linux-androideabi",
+        standalone_dir: "arm",
+        min_api_level: 16,
+        min_ndk_major: 0,
+        by_default: true,
+        variant: TargetPlatform::Armv7,
+    },
+    TargetSpec {
+        triple: "i686-linux-android",
+        abi: "x86",
+        binutils_prefix: "i686-linux-android",
+        clang_prefix: "i686-linux-android",
+        standalone_dir: "x86",
+        min_api_level: 16,
+        min_ndk_major: 0,
+        by_default: true,
+        variant: TargetPlatform::I686,
+    },
+    TargetSpec {
+        triple: "x86_64-linux-android",
+        abi: "x86_64",
+        binutils_prefix: "x86_64-linux-android",
+        clang_prefix: "x86_64-linux-android",
+        standalone_dir: "x86_64",
+        min_api_level: 21,
+        min_ndk_major: 0,
+        by_default: true,
+        variant: TargetPlatform::X86_64,
+    },
+    TargetSpec {
+        triple: "riscv64-linux-android",
+        abi: "riscv64",
+        binutils_prefix: "riscv64-linux-android",
+        clang_prefix: "riscv64-linux-android",
+        standalone_dir: "riscv64",
+        min_api_level: 35,
+        min_ndk_major: 26,
+        by_default: false,
+        variant: TargetPlatform::Riscv64,
+    },
+];
+
 #[derive(PartialEq, Eq, Hash)]
 pub struct PlatformToolset {
     target: &'static str,
@@ -121,19 +1251,6 @@ impl PlatformToolset {
         })
     }
 
-    pub fn format_target(&self) -> String {
-        format!(
-            r#"
-[target.{}]
-ar = "{}"
-linker = "{}"
-"#,
-            self.target(),
-            self.ar(),
-            self.linker()
-        )
-    }
-
     pub fn target(&self) -> &str {
         self.target
     }
@@ -152,6 +1269,8 @@ pub enum TargetPlatform {
     Aarch64(PlatformToolset),
     Armv7(PlatformToolset),
     I686(PlatformToolset),
+    X86_64(PlatformToolset),
+    Riscv64(PlatformToolset),
 }
 
 impl TargetPlatform {
@@ -166,6 +1285,12 @@ impl TargetPlatform {
             TargetPlatform::I686(i686) => {
                 Ok(TargetPlatform::I686(i686.clone_with_ndk_root(root_path)?))
             }
+            TargetPlatform::X86_64(x86_64) => Ok(TargetPlatform::X86_64(
+                x86_64.clone_with_ndk_root(root_path)?,
+            )),
+            TargetPlatform::Riscv64(riscv64) => Ok(TargetPlatform::Riscv64(
+                riscv64.clone_with_ndk_root(root_path)?,
+            )),
         }
     }
 
@@ -174,53 +1299,253 @@ impl TargetPlatform {
             TargetPlatform::Aarch64(aarch64) => aarch64,
             TargetPlatform::Armv7(armv7) => armv7,
             TargetPlatform::I686(i686) => i686,
+            TargetPlatform::X86_64(x86_64) => x86_64,
+            TargetPlatform::Riscv64(riscv64) => riscv64,
         }
     }
+
+    pub fn spec(&self) -> &'static TargetSpec {
+        let triple = self.to_platform_toolset().target();
+        TARGET_SPECS
+            .iter()
+            .find(|spec| spec.triple == triple)
+            .expect("every target has a spec")
+    }
+
+    #[cfg(test)]
+    pub fn abi(&self) -> &'static str {
+        self.spec().abi
+    }
 }
 
+/// Marks the `[target.<triple>]` tables which andrust writes in the cargo config.
+const MANAGED_MARKER: &str = "# managed by andrust";
+
 pub struct ConfigWriter<'a> {
     toolsets: &'a HashSet<TargetPlatform>,
+    link_args: Option<&'a LinkArgs>,
+}
+
+/// What merging the toolsets did to a `[target.<triple>]` table.
+#[derive(PartialEq, Debug)]
+pub enum ConfigChange {
+    Added(String),
+    Updated(String, Vec<String>),
+    Unchanged(String),
+}
+
+impl fmt::Display for ConfigChange {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigChange::Added(triple) => write!(formatter, "added [target.{}]", triple),
+            ConfigChange::Updated(triple, keys) => write!(
+                formatter,
+                "updated {} of [target.{}]",
+                keys.join(", "),
+                triple
+            ),
+            ConfigChange::Unchanged(triple) => {
+                write!(formatter, "[target.{}] is up to date", triple)
+            }
+        }
+    }
 }
 
 impl<'a> ConfigWriter<'a> {
     pub fn new(toolsets: &'a HashSet<TargetPlatform>) -> Self {
-        ConfigWriter { toolsets }
+        ConfigWriter {
+            toolsets,
+            link_args: None,
+        }
     }
 
-    fn content(&self) -> String {
-        let mut config_content = String::new();
-
-        for target_toolset in self.toolsets {
-            let toolset = target_toolset.to_platform_toolset();
-            config_content.push_str(toolset.format_target().as_str());
-        }
-        config_content
+    /// Also sets `rustflags` of the targets which have extra linker arguments.
+    pub fn with_link_args(mut self, link_args: &'a LinkArgs) -> Self {
+        self.link_args = Some(link_args);
+        self
     }
 
-    pub fn write(self, proj_root: Option<PathBuf>) {
-        use std::fs;
-        use std::io::Write;
+    /// Sets `ar`, `linker` and `rustflags` of the `[target.<triple>]` tables in the cargo
+    /// config `existing`.
+    ///
+    /// Other tables, keys and comments are kept as they are, and added tables are marked
+    /// as managed by andrust.
+    pub fn merge(&self, existing: &str) -> PlatformResult<(String, Vec<ConfigChange>)> {
+        use toml_edit::{value, Array, DocumentMut, Item, Table, TableLike};
 
-        let file_name = ".cargo/config";
-        let path = proj_root
-            .map(|mut root| {
-                root.push("/");
-                root.push(file_name);
-                root
+        let mut document: DocumentMut = existing
+            .parse()
+            .map_err(|err: toml_edit::TomlError| PlatformError::InvalidConfig(err.to_string()))?;
+
+        let mut targets: Vec<&TargetPlatform> = self.toolsets.iter().collect();
+        targets.sort_by_key(|target| target.to_platform_toolset().target());
+
+        let is_empty = document.as_table().is_empty();
+        let tables = document
+            .entry("target")
+            .or_insert_with(|| {
+                let mut targets = Table::new();
+                targets.set_implicit(true);
+                Item::Table(targets)
             })
-            .unwrap_or(PathBuf::from(file_name));
-        path.parent().and_then(|parent_path| {
-            if !parent_path.exists() {
-                fs::create_dir_all(parent_path).ok()
+            .as_table_like_mut()
+            .ok_or_else(|| PlatformError::InvalidConfig("target is not a table".to_owned()))?;
+
+        let mut changes = Vec::new();
+        for (index, target) in targets.into_iter().enumerate() {
+            let toolset = target.to_platform_toolset();
+            let triple = toolset.target();
+            let rustflags = self
+                .link_args
+                .map(|link_args| link_args.rustflags(target.spec()))
+                .unwrap_or_default();
+            // An inline table such as `aarch64-linux-android = { runner = "x" }` is kept inline.
+            let table: &mut dyn TableLike =
+                match tables.get_mut(triple).and_then(Item::as_table_like_mut) {
+                    Some(table) => table,
+                    None => {
+                        let mut table = Table::new();
+                        let separator = if is_empty && index == 0 { "" } else { "\n" };
+                        table
+                            .decor_mut()
+                            .set_prefix(format!("{}{}\n", separator, MANAGED_MARKER));
+                        table["ar"] = value(toolset.ar());
+                        table["linker"] = value(toolset.linker());
+                        if !rustflags.is_empty() {
+                            table["rustflags"] = value(rustflags.iter().collect::<Array>());
+                        }
+                        tables.insert(triple, Item::Table(table));
+                        changes.push(ConfigChange::Added(triple.to_owned()));
+                        continue;
+                    }
+                };
+
+            let mut updated = Vec::new();
+            for (key, tool) in &[("ar", toolset.ar()), ("linker", toolset.linker())] {
+                if table.get(key).and_then(Item::as_str) != Some(tool) {
+                    table.insert(key, value(*tool));
+                    updated.push(key.to_string());
+                }
+            }
+            let existing_flags: Option<Vec<&str>> = table
+                .get("rustflags")
+                .and_then(Item::as_array)
+                .map(|flags| flags.iter().filter_map(|flag| flag.as_str()).collect());
+            if !rustflags.is_empty()
+                && existing_flags != Some(rustflags.iter().map(String::as_str).collect())
+            {
+                table.insert("rustflags", value(rustflags.iter().collect::<Array>()));
+                updated.push("rustflags".to_owned());
+            }
+            if updated.is_empty() {
+                changes.push(ConfigChange::Unchanged(triple.to_owned()));
             } else {
-                Some(())
+                changes.push(ConfigChange::Updated(triple.to_owned(), updated));
             }
-        });
+        }
+
+        Ok((document.to_string(), changes))
+    }
+
+    /// The cargo config of `proj_root` which is written.
+    pub fn path(&self, proj_root: &Path) -> PathBuf {
+        ConfigWriter::path_of(proj_root)
+    }
+
+    pub fn path_of(proj_root: &Path) -> PathBuf {
+        project::cargo_config_path(proj_root, project::cargo_version())
+    }
+
+    /// The content of the cargo config at `path`, which is empty when it does not exist.
+    pub fn read(path: &Path) -> PlatformResult<String> {
+        match fs::read_to_string(path) {
+            Ok(existing) => Ok(existing),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+            Err(err) => Err(PlatformError::InvalidConfig(err.to_string())),
+        }
+    }
+
+    pub fn write(self, proj_root: &Path) -> PlatformResult<()> {
+        let path = self.path(proj_root);
+        let existing = ConfigWriter::read(path.as_path())?;
+        let (content, changes) = self.merge(existing.as_str())?;
+        if content != existing {
+            ConfigWriter::save(proj_root, path.as_path(), content.as_str())?;
+        }
+
+        for change in changes {
+            println!("{} : {}", path.display(), change);
+        }
+        Ok(())
+    }
+
+    /// Removes the `[target.<triple>]` tables marked as managed by andrust from `existing`.
+    pub fn remove_managed(existing: &str) -> PlatformResult<(String, Vec<String>)> {
+        use toml_edit::{DocumentMut, Item};
 
-        let mut file = fs::File::create(file_name).expect("file error");
+        let mut document: DocumentMut = existing
+            .parse()
+            .map_err(|err: toml_edit::TomlError| PlatformError::InvalidConfig(err.to_string()))?;
 
-        let content = self.content();
-        file.write_all(content.as_bytes()).unwrap();
+        let mut removed = Vec::new();
+        if let Some(targets) = document.get_mut("target").and_then(Item::as_table_mut) {
+            let managed: Vec<String> = targets
+                .iter()
+                .filter(|(_, item)| {
+                    item.as_table()
+                        .and_then(|table| table.decor().prefix())
+                        .and_then(|prefix| prefix.as_str())
+                        .map(|prefix| prefix.contains(MANAGED_MARKER))
+                        .unwrap_or(false)
+                })
+                .map(|(triple, _)| triple.to_owned())
+                .collect();
+            for triple in managed {
+                targets.remove(triple.as_str());
+                removed.push(triple);
+            }
+            if targets.is_empty() {
+                document.remove("target");
+            }
+        }
+        Ok((document.to_string(), removed))
+    }
+
+    /// Removes the tables managed by andrust from the cargo config of `proj_root`.
+    pub fn unconfigure(proj_root: &Path) -> PlatformResult<()> {
+        let path = ConfigWriter::path_of(proj_root);
+        let existing = ConfigWriter::read(path.as_path())?;
+        let (content, removed) = ConfigWriter::remove_managed(existing.as_str())?;
+        if removed.is_empty() {
+            println!("{} has no target managed by andrust", path.display());
+            return Ok(());
+        }
+
+        ConfigWriter::save(proj_root, path.as_path(), content.as_str())?;
+        for triple in removed {
+            println!("{} : removed [target.{}]", path.display(), triple);
+        }
+        Ok(())
+    }
+
+    /// Writes `content` to the cargo config at `path`, backing up the previous one.
+    fn save(proj_root: &Path, path: &Path, content: &str) -> PlatformResult<()> {
+        let backup = BackupStore::new(proj_root)
+            .backup(path)
+            .map_err(|err| PlatformError::InvalidConfig(err.to_string()))?;
+        if let Some(backup) = backup {
+            println!(
+                "the previous {} is backed up as {}",
+                path.display(),
+                backup.id()
+            );
+        }
+
+        if let Some(parent_path) = path.parent() {
+            fs::create_dir_all(parent_path)
+                .map_err(|err| PlatformError::InvalidConfig(err.to_string()))?;
+        }
+        fs::write(path, content).map_err(|err| PlatformError::InvalidConfig(err.to_string()))
     }
 }
 
@@ -230,13 +1555,55 @@ type PlatformResult<T> = Result<T, PlatformError>;
 pub enum PlatformError {
     ToolsetDoesNotExist,
     WrongPathName,
+    DownloadFailed(String),
+    UnarchiveFailed(String),
+    NdkVersionMismatch(String),
+    UnsupportedApiLevel(String),
+    UnsupportedTarget(String),
+    InvalidConfig(String),
+    NotInstalled(String),
+    InteractionRequired(Prompt),
+    TerminalFailed(String),
 }
 
 impl fmt::Display for PlatformError {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
+        match self {
             PlatformError::ToolsetDoesNotExist => write!(formatter, "ToolsetDoesNotExist"),
             PlatformError::WrongPathName => write!(formatter, "WrongPathName"),
+            PlatformError::DownloadFailed(reason) => {
+                write!(formatter, "DownloadFailed : {}", reason)
+            }
+            PlatformError::UnarchiveFailed(reason) => {
+                write!(formatter, "UnarchiveFailed : {}", reason)
+            }
+            PlatformError::NdkVersionMismatch(reason) => {
+                write!(formatter, "NdkVersionMismatch : {}", reason)
+            }
+            PlatformError::UnsupportedApiLevel(reason) => {
+                write!(formatter, "UnsupportedApiLevel : {}", reason)
+            }
+            PlatformError::UnsupportedTarget(reason) => {
+                write!(formatter, "UnsupportedTarget : {}", reason)
+            }
+            PlatformError::InvalidConfig(reason) => write!(formatter, "InvalidConfig : {}", reason),
+            PlatformError::NotInstalled(reason) => write!(formatter, "NotInstalled : {}", reason),
+            PlatformError::InteractionRequired(prompt) => {
+                write!(formatter, "InteractionRequired : {}", prompt)
+            }
+            PlatformError::TerminalFailed(reason) => {
+                write!(formatter, "TerminalFailed : {}", reason)
+            }
+        }
+    }
+}
+
+impl PlatformError {
+    /// The exit code of andrust, which is distinct for each prompt in non-interactive mode.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            PlatformError::InteractionRequired(prompt) => prompt.exit_code(),
+            _ => 1,
         }
     }
 }
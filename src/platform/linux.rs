@@ -5,8 +5,8 @@ use std::{
 };
 
 use super::{
-    super::command::CommandOptions, super::downloader, ConfigWriter, Platform, PlatformError,
-    PlatformResult, PlatformToolset, TargetPlatform,
+    super::command::CommandOptions, HostToolchain, Platform, PlatformError, PlatformResult,
+    TargetPlatform, LINUX_X86_64,
 };
 
 pub struct LinuxConfig {
@@ -15,7 +15,7 @@ pub struct LinuxConfig {
 }
 
 impl Platform for LinuxConfig {
-    fn search_ndk_root() -> Option<PathBuf> {
+    fn search_ndk_root(platform_toolsets: &HashSet<TargetPlatform>) -> Option<PathBuf> {
         env::var("NDK_TOOL_ROOT")
             .or(env::var("ANDROID_HOME")
                 .map(|sdk_root| format!("{}/ndk-bundle", sdk_root.as_str())))
@@ -29,13 +29,30 @@ impl Platform for LinuxConfig {
                     None
                 }
             })
+            .map(|ndk_root| PathBuf::from(ndk_root.as_str()))
             .or_else(|| {
                 env::var("ANDROID_HOME").ok().and_then(|path| {
                     let ndk_root = format!("{}/ndk", path.as_str());
-                    LinuxConfig::get_latest_folder_name(ndk_root.as_str())
+                    LinuxConfig::get_latest_ndk_root(ndk_root.as_str(), platform_toolsets)
                 })
             })
-            .map(|ndk_root| PathBuf::from(ndk_root.as_str()))
+            .or_else(|| LinuxConfig::installed_ndk_root(platform_toolsets))
+    }
+
+    fn ndk_install_root() -> Option<PathBuf> {
+        env::var("XDG_DATA_HOME")
+            .ok()
+            .filter(|data_home| !data_home.is_empty())
+            .or_else(|| {
+                env::var("HOME")
+                    .map(|home_path| format!("{}/.local/share", home_path.as_str()))
+                    .ok()
+            })
+            .map(|data_home| PathBuf::from(format!("{}/andrust/ndk", data_home.as_str())))
+    }
+
+    fn host_toolchain() -> &'static HostToolchain {
+        &LINUX_X86_64
     }
 
     fn determine_ndk_root(&self) -> PlatformResult<PathBuf> {
@@ -44,25 +61,23 @@ impl Platform for LinuxConfig {
             .as_ref()
             .and_then(|cmd_opt| cmd_opt.ndk_root());
 
-        if input_ndk_root.is_some()
-            && LinuxConfig::does_toolsets_exist(input_ndk_root.unwrap(), self.targets())
+        if let Some(input_ndk_root) = input_ndk_root
+            .filter(|ndk_root| LinuxConfig::does_toolsets_exist(ndk_root, self.targets()))
         {
-            let verified_ndk_root = input_ndk_root.unwrap().to_path_buf();
-            return Ok(verified_ndk_root);
+            return Ok(input_ndk_root.to_path_buf());
         } else {
             println!("input ndk root is not verified, ndk root candidates are being searched.");
         }
 
-        let root_path = LinuxConfig::search_ndk_root()
-            .or_else(|| {
-                let user_input_path = LinuxConfig::ask_ndk_root();
-                let path = PathBuf::from(user_input_path.as_str());
-                if path.exists() {
-                    Some(path)
-                } else {
-                    None
-                }
-            })
+        let found = LinuxConfig::search_ndk_root(self.targets()).or_else(|| {
+            LinuxConfig::select_latest_ndk_root(self.configured_sdk_ndk_roots(), self.targets())
+        });
+        let found = match found {
+            Some(ndk_root) => Some(ndk_root),
+            None => self.ask_verified_ndk_root()?,
+        };
+
+        found
             .and_then(|path| {
                 let toolsets = self.targets();
                 let does_exist = LinuxConfig::does_toolsets_exist(path.as_path(), toolsets);
@@ -71,97 +86,30 @@ impl Platform for LinuxConfig {
                 } else {
                     None
                 }
-            });
-
-        //FIXME: following code is for downloading
-        // let download = || {
-        //     use crate::downloader::{BuildPlatformConfig, Downloader};
-
-        //     let config = BuildPlatformConfig::default();
-        //     let downloader = Downloader::default();
-        //     let _ = downloader.download(config.download_url().unwrap().parse().unwrap(), "ndk.zip");
-
-        //     env::var("HOME")
-        //         .map(|home_path| format!("{}/tools", home_path.as_str()))
-        //         .ok()
-        // };
-
-        root_path.ok_or(PlatformError::ToolsetDoesNotExist)
+            })
+            .ok_or(PlatformError::ToolsetDoesNotExist)
     }
 
     fn targets(&self) -> &HashSet<TargetPlatform> {
         &self.targets
     }
 
-    fn setup_config(self, root_path: &Path) {
-        use std::iter::FromIterator;
-
-        let toolsets = self
-            .targets
-            .into_iter()
-            .map(|target| target.add_ndk_root(root_path))
-            .filter(|target_adding_result| target_adding_result.is_ok())
-            .map(|filtered_target| filtered_target.unwrap());
-        let toolsets = HashSet::from_iter(toolsets);
-
-        let writer = ConfigWriter::new(&toolsets);
-        writer.write(None);
+    fn cmd_opts(&self) -> Option<&CommandOptions> {
+        self.cmd_opts.as_ref()
     }
 
-    //TODO: implement download logic
-    fn download_ndk() {
-        let downloader = downloader::Downloader::default();
-        let _ = downloader.download(
-            "https://dl.google.com/android/repository/android-ndk-r21b-linux-x86_64.zip"
-                .parse()
-                .unwrap(),
-            "ndk.zip",
-        );
+    fn setup_config(self, root_path: &Path) -> PlatformResult<()> {
+        let toolsets = self.configured_toolsets(root_path)?;
+        self.write_config(root_path, &toolsets)
     }
 }
 
 impl LinuxConfig {
     pub fn new(cmd_opts: Option<CommandOptions>) -> Self {
-        let toolsets = LinuxConfig::get_toolsets();
+        let toolsets = LinuxConfig::selected_toolsets(cmd_opts.as_ref());
         LinuxConfig {
             targets: toolsets,
             cmd_opts,
         }
     }
-
-    fn get_toolsets() -> HashSet<TargetPlatform> {
-        let aarch64_ar = "{}/toolchains/llvm/prebuilt/windows-x86_64/bin/aarch64-linux-android-ar";
-        let aarch64_linker =
-            "{}/toolchains/llvm/prebuilt/windows-x86_64/bin/aarch64-linux-android21-clang.cmd";
-        let aarch64 = PlatformToolset::new(
-            "aarch64-linux-android",
-            aarch64_ar.to_owned(),
-            aarch64_linker.to_owned(),
-        );
-
-        let armv7_ar = "{}/toolchains/llvm/prebuilt/windows-x86_64/bin/arm-linux-androideabi-ar";
-        let armv7_linker =
-            "{}/toolchains/llvm/prebuilt/windows-x86_64/bin/armv7a-linux-androideabi16-clang.cmd";
-        let armv7 = PlatformToolset::new(
-            "armv7-linux-androideabi",
-            armv7_ar.to_owned(),
-            armv7_linker.to_owned(),
-        );
-
-        let i686_ar = "{}/toolchains/llvm/prebuilt/windows-x86_64/bin/i686-linux-android-ar";
-        let i686_linker =
-            "{}/toolchains/llvm/prebuilt/windows-x86_64/bin/i686-linux-android16-clang.cmd";
-        let i686 = PlatformToolset::new(
-            "i686-linux-android",
-            i686_ar.to_owned(),
-            i686_linker.to_owned(),
-        );
-
-        let mut toolsets = HashSet::new();
-        toolsets.insert(TargetPlatform::Aarch64(aarch64));
-        toolsets.insert(TargetPlatform::Armv7(armv7));
-        toolsets.insert(TargetPlatform::I686(i686));
-
-        toolsets
-    }
 }
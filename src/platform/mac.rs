@@ -5,8 +5,8 @@ use std::{
 };
 
 use super::{
-    super::command::CommandOptions, super::downloader, ConfigWriter, Platform, PlatformError,
-    PlatformResult, PlatformToolset, TargetPlatform,
+    super::command::CommandOptions, HostToolchain, Platform, PlatformError, PlatformResult,
+    TargetPlatform, DARWIN_X86_64,
 };
 
 pub struct MacConfig {
@@ -15,7 +15,7 @@ pub struct MacConfig {
 }
 
 impl Platform for MacConfig {
-    fn search_ndk_root() -> Option<PathBuf> {
+    fn search_ndk_root(platform_toolsets: &HashSet<TargetPlatform>) -> Option<PathBuf> {
         env::var("NDK_TOOL_ROOT")
             .or(env::var("ANDROID_HOME")
                 .map(|sdk_root| format!("{}/ndk-bundle", sdk_root.as_str())))
@@ -29,13 +29,30 @@ impl Platform for MacConfig {
                     None
                 }
             })
+            .map(|ndk_root| PathBuf::from(ndk_root.as_str()))
             .or_else(|| {
                 env::var("ANDROID_HOME").ok().and_then(|sdk_root| {
                     let ndk_root = format!("{}/ndk", sdk_root.as_str());
-                    MacConfig::get_latest_folder_name(ndk_root.as_str())
+                    MacConfig::get_latest_ndk_root(ndk_root.as_str(), platform_toolsets)
                 })
             })
-            .map(|ndk_root| PathBuf::from(ndk_root.as_str()))
+            .or_else(|| MacConfig::installed_ndk_root(platform_toolsets))
+    }
+
+    fn ndk_install_root() -> Option<PathBuf> {
+        env::var("XDG_DATA_HOME")
+            .ok()
+            .filter(|data_home| !data_home.is_empty())
+            .or_else(|| {
+                env::var("HOME")
+                    .map(|home_path| format!("{}/Library/Application Support", home_path.as_str()))
+                    .ok()
+            })
+            .map(|data_home| PathBuf::from(format!("{}/andrust/ndk", data_home.as_str())))
+    }
+
+    fn host_toolchain() -> &'static HostToolchain {
+        &DARWIN_X86_64
     }
 
     fn determine_ndk_root(&self) -> PlatformResult<PathBuf> {
@@ -44,25 +61,23 @@ impl Platform for MacConfig {
             .as_ref()
             .and_then(|cmd_opt| cmd_opt.ndk_root());
 
-        if input_ndk_root.is_some()
-            && MacConfig::does_toolsets_exist(input_ndk_root.unwrap(), self.targets())
+        if let Some(input_ndk_root) = input_ndk_root
+            .filter(|ndk_root| MacConfig::does_toolsets_exist(ndk_root, self.targets()))
         {
-            let verified_ndk_root = input_ndk_root.unwrap().to_path_buf();
-            return Ok(verified_ndk_root);
+            return Ok(input_ndk_root.to_path_buf());
         } else {
             println!("input ndk root is not verified, ndk root candidates are being searched.");
         }
 
-        MacConfig::search_ndk_root()
-            .or_else(|| {
-                let user_input_path = MacConfig::ask_ndk_root();
-                let path = PathBuf::from(user_input_path.as_str());
-                if path.exists() {
-                    Some(path)
-                } else {
-                    None
-                }
-            })
+        let found = MacConfig::search_ndk_root(self.targets()).or_else(|| {
+            MacConfig::select_latest_ndk_root(self.configured_sdk_ndk_roots(), self.targets())
+        });
+        let found = match found {
+            Some(ndk_root) => Some(ndk_root),
+            None => self.ask_verified_ndk_root()?,
+        };
+
+        found
             .and_then(|path| {
                 let toolsets = self.targets();
                 let does_exist = MacConfig::does_toolsets_exist(path.as_path(), toolsets);
@@ -79,36 +94,19 @@ impl Platform for MacConfig {
         &self.targets
     }
 
-    fn setup_config(self, root_path: &Path) {
-        use std::iter::FromIterator;
-
-        let toolsets = self
-            .targets
-            .into_iter()
-            .map(|target| target.add_ndk_root(root_path))
-            .filter(|target_adding_result| target_adding_result.is_ok())
-            .map(|filtered_target| filtered_target.unwrap());
-        let toolsets = HashSet::from_iter(toolsets);
-
-        let writer = ConfigWriter::new(&toolsets);
-        writer.write(None);
+    fn cmd_opts(&self) -> Option<&CommandOptions> {
+        self.cmd_opts.as_ref()
     }
 
-    //TODO: implement download logic
-    fn download_ndk() {
-        let downloader = downloader::Downloader::default();
-        let _ = downloader.download(
-            "https://dl.google.com/android/repository/android-ndk-r21b-darwin-x86_64.zip"
-                .parse()
-                .unwrap(),
-            "ndk.zip",
-        );
+    fn setup_config(self, root_path: &Path) -> PlatformResult<()> {
+        let toolsets = self.configured_toolsets(root_path)?;
+        self.write_config(root_path, &toolsets)
     }
 }
 
 impl Default for MacConfig {
     fn default() -> Self {
-        let toolsets = MacConfig::get_toolsets();
+        let toolsets = MacConfig::selected_toolsets(None);
         MacConfig {
             targets: toolsets,
             cmd_opts: None,
@@ -117,46 +115,12 @@ impl Default for MacConfig {
 }
 
 impl MacConfig {
+    #[cfg(target_os = "macos")]
     pub fn new(cmd_opts: Option<CommandOptions>) -> Self {
-        let toolsets = MacConfig::get_toolsets();
+        let toolsets = MacConfig::selected_toolsets(cmd_opts.as_ref());
         MacConfig {
             targets: toolsets,
             cmd_opts,
         }
     }
-
-    fn get_toolsets() -> HashSet<TargetPlatform> {
-        let aarch64_ar = "toolchains/llvm/prebuilt/darwin-x86_64/bin/aarch64-linux-android-ar";
-        let aarch64_linker =
-            "toolchains/llvm/prebuilt/darwin-x86_64/bin/aarch64-linux-android21-clang";
-        let aarch64 = PlatformToolset::new(
-            "aarch64-linux-android",
-            aarch64_ar.to_owned(),
-            aarch64_linker.to_owned(),
-        );
-
-        let armv7_ar = "toolchains/llvm/prebuilt/darwin-x86_64/bin/arm-linux-androideabi-ar";
-        let armv7_linker =
-            "toolchains/llvm/prebuilt/darwin-x86_64/bin/armv7a-linux-androideabi16-clang";
-        let armv7 = PlatformToolset::new(
-            "armv7-linux-androideabi",
-            armv7_ar.to_owned(),
-            armv7_linker.to_owned(),
-        );
-
-        let i686_ar = "toolchains/llvm/prebuilt/darwin-x86_64/bin/i686-linux-android-ar";
-        let i686_linker = "toolchains/llvm/prebuilt/darwin-x86_64/bin/i686-linux-android16-clang";
-        let i686 = PlatformToolset::new(
-            "i686-linux-android",
-            i686_ar.to_owned(),
-            i686_linker.to_owned(),
-        );
-
-        let mut toolsets = HashSet::new();
-        toolsets.insert(TargetPlatform::Aarch64(aarch64));
-        toolsets.insert(TargetPlatform::Armv7(armv7));
-        toolsets.insert(TargetPlatform::I686(i686));
-
-        toolsets
-    }
 }
@@ -6,8 +6,8 @@ use std::{
 };
 
 use super::{
-    super::command::CommandOptions, super::downloader, ConfigWriter, Platform, PlatformError,
-    PlatformResult, PlatformToolset, TargetPlatform,
+    super::command::CommandOptions, HostToolchain, Platform, PlatformError, PlatformResult,
+    TargetPlatform, WINDOWS_X86_64,
 };
 
 pub struct WinConfig {
@@ -16,7 +16,7 @@ pub struct WinConfig {
 }
 
 impl Platform for WinConfig {
-    fn search_ndk_root() -> Option<PathBuf> {
+    fn search_ndk_root(platform_toolsets: &HashSet<TargetPlatform>) -> Option<PathBuf> {
         env::var("NDK_TOOL_ROOT")
             .ok()
             .and_then(|ndk_root| {
@@ -26,13 +26,26 @@ impl Platform for WinConfig {
                     None
                 }
             })
+            .map(|ndk_root| PathBuf::from(ndk_root.as_str()))
             .or_else(|| {
                 env::var("ANDROID_HOME").ok().and_then(|sdk_root| {
                     let ndk_root = format!("{}/ndk", sdk_root.as_str());
-                    WinConfig::get_latest_folder_name(ndk_root.as_str())
+                    WinConfig::get_latest_ndk_root(ndk_root.as_str(), platform_toolsets)
                 })
             })
-            .map(|ndk_root| PathBuf::from(ndk_root.as_str()))
+            .or_else(|| WinConfig::installed_ndk_root(platform_toolsets))
+    }
+
+    fn ndk_install_root() -> Option<PathBuf> {
+        env::var("XDG_DATA_HOME")
+            .ok()
+            .filter(|data_home| !data_home.is_empty())
+            .or_else(|| env::var("LOCALAPPDATA").ok())
+            .map(|data_home| PathBuf::from(format!("{}/andrust/ndk", data_home.as_str())))
+    }
+
+    fn host_toolchain() -> &'static HostToolchain {
+        &WINDOWS_X86_64
     }
 
     fn determine_ndk_root(&self) -> PlatformResult<PathBuf> {
@@ -41,25 +54,23 @@ impl Platform for WinConfig {
             .as_ref()
             .and_then(|cmd_opt| cmd_opt.ndk_root());
 
-        if input_ndk_root.is_some()
-            && WinConfig::does_toolsets_exist(input_ndk_root.unwrap(), self.targets())
+        if let Some(input_ndk_root) = input_ndk_root
+            .filter(|ndk_root| WinConfig::does_toolsets_exist(ndk_root, self.targets()))
         {
-            let verified_ndk_root = input_ndk_root.unwrap().to_path_buf();
-            return Ok(verified_ndk_root);
+            return Ok(input_ndk_root.to_path_buf());
         } else {
             println!("input ndk root is not verified, ndk root candidates are being searched.");
         }
 
-        WinConfig::search_ndk_root()
-            .or_else(|| {
-                let user_input_path = WinConfig::ask_ndk_root();
-                let path = PathBuf::from(user_input_path.as_str());
-                if path.exists() {
-                    Some(path)
-                } else {
-                    None
-                }
-            })
+        let found = WinConfig::search_ndk_root(self.targets()).or_else(|| {
+            WinConfig::select_latest_ndk_root(self.configured_sdk_ndk_roots(), self.targets())
+        });
+        let found = match found {
+            Some(ndk_root) => Some(ndk_root),
+            None => self.ask_verified_ndk_root()?,
+        };
+
+        found
             .and_then(|path| {
                 let toolsets = self.targets();
                 let does_exist = WinConfig::does_toolsets_exist(path.as_path(), toolsets);
@@ -76,95 +87,19 @@ impl Platform for WinConfig {
         &self.targets
     }
 
-    fn setup_config(self, ndk_root: &Path) {
-        use std::iter::FromIterator;
-
-        let toolsets = self
-            .targets
-            .into_iter()
-            .map(|target| target.add_ndk_root(ndk_root))
-            .filter(|target_adding_result| target_adding_result.is_ok())
-            .map(|filtered_target| filtered_target.unwrap());
-        let toolsets = HashSet::from_iter(toolsets);
-
-        let proj_root = self
-            .cmd_opts
-            .and_then(|opts| opts.proj_root().map(|path| path.to_path_buf()));
-        let writer = ConfigWriter::new(&toolsets);
-        writer.write(proj_root);
+    fn cmd_opts(&self) -> Option<&CommandOptions> {
+        self.cmd_opts.as_ref()
     }
 
-    fn does_toolsets_exist(ndk_root: &Path, platform_toolsets: &HashSet<TargetPlatform>) -> bool {
-        let mut does_all_exist = true;
-        for target_toolset in platform_toolsets {
-            let toolsets = target_toolset.to_platform_toolset();
-            let ndk_root = match ndk_root.to_str() {
-                Some(path) => path,
-                None => return false,
-            };
-            let ar_path = format!("{}/{}", ndk_root, toolsets.ar());
-            let linker_path = format!("{}/{}", ndk_root, toolsets.linker());
-
-            does_all_exist = does_all_exist
-                && Path::new(ar_path.as_str()).exists()
-                && Path::new(linker_path.as_str()).exists();
-
-            if !does_all_exist {
-                break;
-            }
-        }
-        does_all_exist
-    }
-
-    fn get_latest_folder_name(root_path: &str) -> Option<String> {
-        let root_path = Path::new(root_path);
-        if !root_path.is_dir() {
-            return None;
-        }
-
-        let mut latest_folder = None;
-        for entry in std::fs::read_dir(root_path).ok()? {
-            let entry = entry.ok()?;
-            let entry_mod_time = entry.metadata().ok()?.modified().ok()?;
-
-            let latest_folder_mod_time = latest_folder
-                .as_ref()
-                .and_then(|folder_name: &String| {
-                    Path::new(folder_name.as_str())
-                        .metadata()
-                        .ok()?
-                        .modified()
-                        .ok()
-                })
-                .unwrap_or(std::time::SystemTime::UNIX_EPOCH);
-
-            if latest_folder_mod_time < entry_mod_time {
-                latest_folder = entry
-                    .path()
-                    .as_path()
-                    .to_str()
-                    .map(|folder_name| folder_name.to_owned());
-            }
-        }
-
-        latest_folder
-    }
-
-    //TODO: implement download logic
-    fn download_ndk() {
-        let downloader = downloader::Downloader::default();
-        let _ = downloader.download(
-            "https://dl.google.com/android/repository/android-ndk-r21b-windows-x86_64.zip"
-                .parse()
-                .unwrap(),
-            "ndk.zip",
-        );
+    fn setup_config(self, ndk_root: &Path) -> PlatformResult<()> {
+        let toolsets = self.configured_toolsets(ndk_root)?;
+        self.write_config(ndk_root, &toolsets)
     }
 }
 
 impl Default for WinConfig {
     fn default() -> Self {
-        let toolsets = WinConfig::get_toolsets();
+        let toolsets = WinConfig::selected_toolsets(None);
         WinConfig {
             targets: toolsets,
             cmd_opts: None,
@@ -173,47 +108,12 @@ impl Default for WinConfig {
 }
 
 impl WinConfig {
+    #[cfg(target_os = "windows")]
     pub fn new(cmd_opts: Option<CommandOptions>) -> Self {
-        let toolsets = WinConfig::get_toolsets();
+        let toolsets = WinConfig::selected_toolsets(cmd_opts.as_ref());
         WinConfig {
             targets: toolsets,
             cmd_opts,
         }
     }
-
-    fn get_toolsets() -> HashSet<TargetPlatform> {
-        let aarch64_ar = "toolchains/llvm/prebuilt/windows-x86_64/bin/aarch64-linux-android-ar.exe";
-        let aarch64_linker =
-            "toolchains/llvm/prebuilt/windows-x86_64/bin/aarch64-linux-android21-clang.cmd";
-        let aarch64 = PlatformToolset::new(
-            "aarch64-linux-android",
-            aarch64_ar.to_owned(),
-            aarch64_linker.to_owned(),
-        );
-
-        let armv7_ar = "toolchains/llvm/prebuilt/windows-x86_64/bin/arm-linux-androideabi-ar.exe";
-        let armv7_linker =
-            "toolchains/llvm/prebuilt/windows-x86_64/bin/armv7a-linux-androideabi16-clang.cmd";
-        let armv7 = PlatformToolset::new(
-            "armv7-linux-androideabi",
-            armv7_ar.to_owned(),
-            armv7_linker.to_owned(),
-        );
-
-        let i686_ar = "toolchains/llvm/prebuilt/windows-x86_64/bin/i686-linux-android-ar.exe";
-        let i686_linker =
-            "toolchains/llvm/prebuilt/windows-x86_64/bin/i686-linux-android16-clang.cmd";
-        let i686 = PlatformToolset::new(
-            "i686-linux-android",
-            i686_ar.to_owned(),
-            i686_linker.to_owned(),
-        );
-
-        let mut toolsets = HashSet::new();
-        toolsets.insert(TargetPlatform::Aarch64(aarch64));
-        toolsets.insert(TargetPlatform::Armv7(armv7));
-        toolsets.insert(TargetPlatform::I686(i686));
-
-        toolsets
-    }
 }
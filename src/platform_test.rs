@@ -0,0 +1,409 @@
+use super::{
+    command::CommandState,
+    platform::{
+        ApiLevels, ConfigChange, ConfigWriter, LinkArgs, LinuxConfig, MacConfig, Platform,
+        PlatformError, TargetPlatform, TargetSpec, ToolsetLayout, HOST_TOOLCHAINS, TARGET_SPECS,
+    },
+};
+
+use std::{collections::HashSet, fs, path::Path};
+
+fn create_fake_ndk(ndk_root: &Path, revision: &str, toolsets: &HashSet<TargetPlatform>) {
+    let _ = fs::remove_dir_all(ndk_root);
+    fs::create_dir_all(ndk_root).unwrap();
+    fs::write(
+        ndk_root.join("source.properties"),
+        format!("Pkg.Desc = Android NDK\nPkg.Revision = {}\n", revision),
+    )
+    .unwrap();
+
+    for target in toolsets {
+        let toolset = target.to_platform_toolset();
+        for tool in &[toolset.ar(), toolset.linker()] {
+            let tool_path = ndk_root.join(tool);
+            fs::create_dir_all(tool_path.parent().unwrap()).unwrap();
+            fs::write(tool_path, "").unwrap();
+        }
+    }
+}
+
+#[test]
+fn toolset_layout_test() {
+    let root = std::env::temp_dir().join("andrust_toolset_layout_test");
+    let targets = MacConfig::get_toolsets(ToolsetLayout::Binutils);
+
+    let layouts = vec![
+        ("18.1.5063045", ToolsetLayout::Standalone),
+        ("21.1.6352462", ToolsetLayout::Binutils),
+        ("25.2.9519653", ToolsetLayout::Llvm),
+    ];
+    for (revision, layout) in layouts {
+        let ndk_root = root.join(revision);
+        create_fake_ndk(
+            ndk_root.as_path(),
+            revision,
+            &MacConfig::get_toolsets(layout),
+        );
+
+        assert_eq!(ToolsetLayout::detect(ndk_root.as_path()), layout);
+        assert!(MacConfig::does_toolsets_exist(ndk_root.as_path(), &targets));
+    }
+
+    // An r25 NDK does not have the GNU binutils which r21 had.
+    let mismatched = root.join("mismatched");
+    create_fake_ndk(
+        mismatched.as_path(),
+        "25.2.9519653",
+        &MacConfig::get_toolsets(ToolsetLayout::Binutils),
+    );
+    assert!(!MacConfig::does_toolsets_exist(
+        mismatched.as_path(),
+        &targets
+    ));
+
+    // An NDK of unknown revision is checked with the r19 layout.
+    let unknown = root.join("unknown");
+    create_fake_ndk(
+        unknown.as_path(),
+        "",
+        &MacConfig::get_toolsets(ToolsetLayout::Binutils),
+    );
+    fs::remove_file(unknown.join("source.properties")).unwrap();
+    assert_eq!(
+        ToolsetLayout::detect(unknown.as_path()),
+        ToolsetLayout::Binutils
+    );
+    assert!(MacConfig::does_toolsets_exist(unknown.as_path(), &targets));
+
+    let llvm_ar = MacConfig::get_toolsets(ToolsetLayout::Llvm)
+        .iter()
+        .all(|target| target.to_platform_toolset().ar().ends_with("/llvm-ar"));
+    assert!(llvm_ar);
+    let _ = fs::remove_dir_all(&root);
+}
+
+fn check_host_layout<P: Platform>(root: &Path) {
+    let host = P::host_toolchain();
+    let targets = P::get_toolsets(ToolsetLayout::Llvm);
+    for target in &targets {
+        let toolset = target.to_platform_toolset();
+        let prebuilt_bin = format!("toolchains/llvm/prebuilt/{}/bin/", host.prebuilt_dir);
+        assert!(toolset.ar().starts_with(prebuilt_bin.as_str()));
+        assert!(toolset
+            .ar()
+            .ends_with(format!("llvm-ar{}", host.exe_suffix).as_str()));
+        assert!(toolset
+            .linker()
+            .ends_with(format!("-clang{}", host.linker_suffix).as_str()));
+    }
+
+    let ndk_root = root.join(host.prebuilt_dir);
+    create_fake_ndk(ndk_root.as_path(), "25.2.9519653", &targets);
+    assert!(P::does_toolsets_exist(ndk_root.as_path(), &targets));
+
+    for other in HOST_TOOLCHAINS
+        .iter()
+        .filter(|other| other.prebuilt_dir != host.prebuilt_dir)
+    {
+        let other_root = root.join(other.prebuilt_dir);
+        create_fake_ndk(
+            other_root.as_path(),
+            "25.2.9519653",
+            &other.toolsets(ToolsetLayout::Llvm),
+        );
+        assert!(!P::does_toolsets_exist(other_root.as_path(), &targets));
+    }
+}
+
+#[test]
+fn host_toolchain_test() {
+    use super::platform::WinConfig;
+
+    let root = std::env::temp_dir().join("andrust_host_toolchain_test");
+    check_host_layout::<LinuxConfig>(root.join("linux").as_path());
+    check_host_layout::<MacConfig>(root.join("macos").as_path());
+    check_host_layout::<WinConfig>(root.join("windows").as_path());
+
+    let linux = LinuxConfig::get_toolsets(ToolsetLayout::Binutils);
+    let aarch64 = linux
+        .iter()
+        .map(|target| target.to_platform_toolset())
+        .find(|toolset| toolset.target() == "aarch64-linux-android")
+        .unwrap();
+    assert_eq!(
+        aarch64.linker(),
+        "toolchains/llvm/prebuilt/linux-x86_64/bin/aarch64-linux-android21-clang"
+    );
+    assert_eq!(
+        aarch64.ar(),
+        "toolchains/llvm/prebuilt/linux-x86_64/bin/aarch64-linux-android-ar"
+    );
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn target_spec_test() {
+    let root = std::env::temp_dir().join("andrust_target_spec_test");
+    let targets = LinuxConfig::get_toolsets(ToolsetLayout::Llvm);
+    let mut abis: Vec<_> = targets.iter().map(|target| target.abi()).collect();
+    abis.sort();
+    assert_eq!(
+        abis,
+        vec!["arm64-v8a", "armeabi-v7a", "riscv64", "x86", "x86_64"]
+    );
+
+    let riscv64 = targets
+        .iter()
+        .map(|target| target.to_platform_toolset())
+        .find(|toolset| toolset.target() == "riscv64-linux-android")
+        .unwrap();
+    assert_eq!(
+        riscv64.linker(),
+        "toolchains/llvm/prebuilt/linux-x86_64/bin/riscv64-linux-android35-clang"
+    );
+
+    // riscv64 is only provided by r26 and newer, older NDKs are set up without it.
+    let r25 = root.join("r25");
+    let mut without_riscv64 = LinuxConfig::get_toolsets(ToolsetLayout::Llvm);
+    without_riscv64.retain(|target| target.abi() != "riscv64");
+    create_fake_ndk(r25.as_path(), "25.2.9519653", &without_riscv64);
+    assert!(LinuxConfig::does_toolsets_exist(r25.as_path(), &targets));
+
+    let r26 = root.join("r26");
+    create_fake_ndk(r26.as_path(), "26.1.10909125", &without_riscv64);
+    assert!(!LinuxConfig::does_toolsets_exist(r26.as_path(), &targets));
+    create_fake_ndk(r26.as_path(), "26.1.10909125", &targets);
+    assert!(LinuxConfig::does_toolsets_exist(r26.as_path(), &targets));
+
+    // riscv64 is a tier-3 Rust target, so it is set up only when it is selected.
+    assert!(linux_config(&[])
+        .targets()
+        .iter()
+        .all(|target| target.abi() != "riscv64"));
+    let toolsets = linux_config(&[])
+        .configured_toolsets(r26.as_path())
+        .unwrap();
+    assert_eq!(toolsets.len(), 4);
+    // An explicitly selected target is not left out, the setup fails instead.
+    let toolsets = linux_config(&[])
+        .configured_toolsets(r25.as_path())
+        .unwrap();
+    assert_eq!(toolsets.len(), 4);
+    let riscv64 = linux_config(&["--targets", "aarch64,riscv64"]);
+    match riscv64.configured_toolsets(r25.as_path()) {
+        Err(PlatformError::UnsupportedTarget(reason)) => {
+            assert!(reason.contains("riscv64-linux-android needs NDK r26"))
+        }
+        _ => panic!("riscv64 is not in NDK r25"),
+    }
+    assert!(riscv64.configured_toolsets(r26.as_path()).is_ok());
+    let _ = fs::remove_dir_all(&root);
+}
+
+fn linux_config(args: &[&str]) -> LinuxConfig {
+    match CommandState::from_args(args.iter().copied()) {
+        CommandState::Options(opts) => LinuxConfig::new(Some(*opts)),
+        _ => panic!("invalid options {:?}", args),
+    }
+}
+
+fn linux_config_with_api_levels(api_levels: &str) -> LinuxConfig {
+    linux_config(&["--api-level", api_levels])
+}
+
+fn configured_linker(toolsets: &HashSet<TargetPlatform>, abi: &str) -> String {
+    toolsets
+        .iter()
+        .find(|target| target.abi() == abi)
+        .map(|target| target.to_platform_toolset().linker().to_owned())
+        .unwrap()
+}
+
+#[test]
+fn api_level_test() {
+    let levels = ApiLevels::parse("24, arm64-v8a=26,x86_64-linux-android=30").unwrap();
+    let spec = |name| TargetSpec::find(name).unwrap();
+    assert_eq!(levels.level(spec("armeabi-v7a")), Some(24));
+    assert_eq!(levels.level(spec("aarch64-linux-android")), Some(26));
+    assert_eq!(levels.level(spec("x86_64")), Some(30));
+    assert_eq!(ApiLevels::parse("").unwrap().level(spec("x86")), None);
+    assert!(ApiLevels::parse("mips=21").is_err());
+    assert!(ApiLevels::parse("twenty").is_err());
+
+    let root = std::env::temp_dir().join("andrust_api_level_test");
+    let ndk_root = root.join("25.2.9519653");
+    create_fake_ndk(
+        ndk_root.as_path(),
+        "25.2.9519653",
+        &LinuxConfig::get_toolsets(ToolsetLayout::Llvm),
+    );
+    let bin = ndk_root.join("toolchains/llvm/prebuilt/linux-x86_64/bin");
+    for spec in TARGET_SPECS.iter() {
+        for level in 21..=33 {
+            fs::write(
+                bin.join(format!("{}{}-clang", spec.clang_prefix, level)),
+                "",
+            )
+            .unwrap();
+            fs::write(
+                bin.join(format!("{}{}-clang++", spec.clang_prefix, level)),
+                "",
+            )
+            .unwrap();
+        }
+    }
+    assert_eq!(
+        spec("armeabi-v7a")
+            .available_api_levels(ndk_root.as_path())
+            .into_iter()
+            .collect::<Vec<_>>(),
+        (21..=33).collect::<Vec<_>>()
+    );
+
+    let linux = linux_config_with_api_levels("24,arm64-v8a=26");
+    let toolsets = linux.configured_toolsets(ndk_root.as_path()).unwrap();
+    assert!(
+        configured_linker(&toolsets, "armeabi-v7a").ends_with("armv7a-linux-androideabi24-clang")
+    );
+    assert!(configured_linker(&toolsets, "arm64-v8a").ends_with("aarch64-linux-android26-clang"));
+    assert!(configured_linker(&toolsets, "x86_64").starts_with(ndk_root.to_str().unwrap()));
+
+    // 64-bit ABIs do not exist before API 21.
+    let linux = linux_config_with_api_levels("x86_64=16");
+    let toolsets = linux.configured_toolsets(ndk_root.as_path()).unwrap();
+    assert!(configured_linker(&toolsets, "x86_64").ends_with("x86_64-linux-android21-clang"));
+
+    let linux = linux_config_with_api_levels("x86=40");
+    match linux.configured_toolsets(ndk_root.as_path()) {
+        Err(PlatformError::UnsupportedApiLevel(reason)) => assert!(reason.contains("21, 22")),
+        _ => panic!("API level 40 is not in the NDK"),
+    }
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn config_merge_test() {
+    let existing = r#"# project settings
+[build]
+target-dir = "out"
+
+[alias]
+b = "build" # short
+
+[target.aarch64-linux-android]
+linker = "old-clang"
+rustflags = ["-C", "link-arg=-s"]
+"#;
+    let mut toolsets = LinuxConfig::get_toolsets(ToolsetLayout::Llvm);
+    toolsets.retain(|target| ["arm64-v8a", "x86_64"].contains(&target.abi()));
+    let writer = ConfigWriter::new(&toolsets);
+
+    let (merged, changes) = writer.merge(existing).unwrap();
+    let untouched = &existing[..existing.find("[target.").unwrap()];
+    assert!(merged.starts_with(untouched));
+    assert!(merged.contains(r#"rustflags = ["-C", "link-arg=-s"]"#));
+    assert!(merged.contains("# managed by andrust\n[target.x86_64-linux-android]"));
+    assert!(!merged.contains("old-clang"));
+    assert_eq!(
+        changes,
+        vec![
+            ConfigChange::Updated(
+                "aarch64-linux-android".to_owned(),
+                vec!["ar".to_owned(), "linker".to_owned()]
+            ),
+            ConfigChange::Added("x86_64-linux-android".to_owned()),
+        ]
+    );
+
+    let (merged_again, changes) = writer.merge(merged.as_str()).unwrap();
+    assert_eq!(merged_again, merged);
+    assert!(changes
+        .iter()
+        .all(|change| matches!(change, ConfigChange::Unchanged(_))));
+
+    let (fresh, _) = writer.merge("").unwrap();
+    assert!(fresh.starts_with("# managed by andrust\n[target.aarch64-linux-android]"));
+    assert!(writer.merge("[build").is_err());
+
+    // The linker arguments of andrust.toml replace the rustflags of a target.
+    let mut link_args = LinkArgs::default();
+    link_args.set_common(&["-Wl,-z,max-page-size=16384".to_owned()]);
+    let writer = ConfigWriter::new(&toolsets).with_link_args(&link_args);
+    let (merged, changes) = writer.merge(existing).unwrap();
+    assert!(merged.contains(r#"rustflags = ["-C", "link-arg=-Wl,-z,max-page-size=16384"]"#));
+    assert!(!merged.contains("link-arg=-s"));
+    assert_eq!(
+        changes[0],
+        ConfigChange::Updated(
+            "aarch64-linux-android".to_owned(),
+            vec!["ar".to_owned(), "linker".to_owned(), "rustflags".to_owned()]
+        )
+    );
+    let (_, changes) = writer.merge(merged.as_str()).unwrap();
+    assert!(changes
+        .iter()
+        .all(|change| matches!(change, ConfigChange::Unchanged(_))));
+}
+
+#[test]
+fn config_merge_inline_test() {
+    let existing = r#"[target]
+aarch64-linux-android = { runner = "adb-run", rustflags = ["-C", "link-arg=-s"] }
+"#;
+    let mut toolsets = LinuxConfig::get_toolsets(ToolsetLayout::Llvm);
+    toolsets.retain(|target| ["arm64-v8a", "x86_64"].contains(&target.abi()));
+    let writer = ConfigWriter::new(&toolsets);
+
+    let (merged, changes) = writer.merge(existing).unwrap();
+    let config: toml::Value = toml::from_str(merged.as_str()).unwrap();
+    let aarch64 = &config["target"]["aarch64-linux-android"];
+    assert_eq!(aarch64["runner"].as_str(), Some("adb-run"));
+    assert_eq!(
+        aarch64["rustflags"],
+        toml::Value::Array(vec!["-C".into(), "link-arg=-s".into()])
+    );
+    assert!(aarch64["linker"]
+        .as_str()
+        .unwrap()
+        .ends_with("aarch64-linux-android21-clang"));
+    assert!(config["target"]["x86_64-linux-android"]["linker"].is_str());
+    assert_eq!(
+        changes[0],
+        ConfigChange::Updated(
+            "aarch64-linux-android".to_owned(),
+            vec!["ar".to_owned(), "linker".to_owned()]
+        )
+    );
+
+    let (merged_again, changes) = writer.merge(merged.as_str()).unwrap();
+    assert_eq!(merged_again, merged);
+    assert!(changes
+        .iter()
+        .all(|change| matches!(change, ConfigChange::Unchanged(_))));
+}
+
+#[test]
+fn config_preview_test() {
+    let root = std::env::temp_dir().join("andrust_config_preview_test");
+    let _ = fs::remove_dir_all(&root);
+    let proj_root = root.join("project");
+    fs::create_dir_all(&proj_root).unwrap();
+    let ndk_root = root.join("25.2.9519653");
+    let toolsets = LinuxConfig::get_toolsets(ToolsetLayout::Llvm);
+    create_fake_ndk(ndk_root.as_path(), "25.2.9519653", &toolsets);
+
+    for preview in &["--dry-run", "--diff"] {
+        let linux = linux_config(&[preview, "--root", proj_root.to_str().unwrap()]);
+        assert!(linux.is_preview());
+        let toolsets = linux.configured_toolsets(ndk_root.as_path()).unwrap();
+        linux.write_config(ndk_root.as_path(), &toolsets).unwrap();
+        assert!(!proj_root.join(".cargo").exists());
+    }
+
+    let linux = linux_config(&["--root", proj_root.to_str().unwrap()]);
+    assert!(!linux.is_preview());
+    let toolsets = linux.configured_toolsets(ndk_root.as_path()).unwrap();
+    linux.write_config(ndk_root.as_path(), &toolsets).unwrap();
+    assert!(proj_root.join(".cargo").exists());
+    let _ = fs::remove_dir_all(&root);
+}
@@ -0,0 +1,130 @@
+use std::cell::RefCell;
+use std::io::{stdout, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+use crossterm::{cursor, terminal, QueueableCommand};
+
+const REDRAW_INTERVAL: Duration = Duration::from_millis(200);
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Shows how much of a download is saved.
+///
+/// On a terminal the progress line is redrawn in place, otherwise a plain line
+/// is logged every `LOG_INTERVAL` so that CI logs stay readable.
+pub struct DownloadIndicator {
+    total: Option<u64>,
+    resumed: u64,
+    started_at: Instant,
+    is_terminal: bool,
+    last_report: RefCell<Option<Instant>>,
+}
+
+impl DownloadIndicator {
+    pub fn new(total: Option<u64>, resumed: u64) -> Self {
+        let is_terminal = stdout().is_terminal();
+        if is_terminal {
+            let mut stdout = stdout();
+            let _ = stdout.queue(cursor::SavePosition);
+            let _ = stdout.flush();
+        }
+
+        DownloadIndicator {
+            total,
+            resumed,
+            started_at: Instant::now(),
+            is_terminal,
+            last_report: RefCell::new(None),
+        }
+    }
+
+    pub fn report(&self, saved: u64) {
+        let now = Instant::now();
+        let interval = if self.is_terminal {
+            REDRAW_INTERVAL
+        } else {
+            LOG_INTERVAL
+        };
+        let is_finished = self.total.map(|total| saved >= total).unwrap_or(false);
+        let mut last_report = self.last_report.borrow_mut();
+        let is_due = last_report
+            .map(|last| now.duration_since(last) >= interval)
+            .unwrap_or(true);
+        if !is_due && !is_finished {
+            return;
+        }
+        *last_report = Some(now);
+
+        let line = self.progress_line(saved, now.duration_since(self.started_at));
+        let mut stdout = stdout();
+        if self.is_terminal {
+            let _ = stdout.queue(cursor::RestorePosition);
+            let _ = stdout.queue(terminal::Clear(terminal::ClearType::UntilNewLine));
+            let _ = stdout.write(line.as_bytes());
+            if is_finished {
+                let _ = stdout.write(b"\n");
+            }
+        } else {
+            let _ = writeln!(stdout, "{}", line);
+        }
+        let _ = stdout.flush();
+    }
+
+    fn progress_line(&self, saved: u64, elapsed: Duration) -> String {
+        let downloaded = saved.saturating_sub(self.resumed);
+        let speed = if elapsed.as_secs_f64() > 0.0 {
+            downloaded as f64 / elapsed.as_secs_f64()
+        } else {
+            0.0
+        };
+
+        match self.total {
+            Some(total) if total > 0 => {
+                let percent = saved as f64 * 100.0 / total as f64;
+                let eta = if speed > 0.0 {
+                    format_duration(total.saturating_sub(saved) as f64 / speed)
+                } else {
+                    "--:--".to_owned()
+                };
+                format!(
+                    "{:5.1}% {} / {}  {}/s  ETA {}",
+                    percent,
+                    format_size(saved as f64),
+                    format_size(total as f64),
+                    format_size(speed),
+                    eta
+                )
+            }
+            _ => format!("{}  {}/s", format_size(saved as f64), format_size(speed)),
+        }
+    }
+}
+
+pub fn format_size(bytes: f64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", size as u64, UNITS[unit])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+pub fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        )
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
@@ -0,0 +1,15 @@
+use super::progress;
+
+#[test]
+fn format_size_test() {
+    assert_eq!(progress::format_size(512.0), "512 B");
+    assert_eq!(progress::format_size(1536.0), "1.5 KiB");
+    assert_eq!(progress::format_size(1043332542.0), "995.0 MiB");
+}
+
+#[test]
+fn format_duration_test() {
+    assert_eq!(progress::format_duration(43.2), "00:43");
+    assert_eq!(progress::format_duration(754.0), "12:34");
+    assert_eq!(progress::format_duration(3723.0), "1:02:03");
+}
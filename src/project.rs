@@ -0,0 +1,180 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+pub const MANIFEST_FILE: &str = "Cargo.toml";
+
+/// The root of the project containing `start`.
+///
+/// It is the nearest directory with a `Cargo.toml`, or the workspace above it when one of
+/// its ancestors has a `Cargo.toml` with a `[workspace]` table.
+pub fn find_project_root(start: &Path) -> Option<PathBuf> {
+    let mut project_root = None;
+    for dir in start.ancestors() {
+        let manifest = dir.join(MANIFEST_FILE);
+        if !manifest.is_file() {
+            continue;
+        }
+        if is_workspace(manifest.as_path()) {
+            return Some(dir.to_path_buf());
+        }
+        if project_root.is_none() {
+            project_root = Some(dir.to_path_buf());
+        }
+    }
+    project_root
+}
+
+fn is_workspace(manifest: &Path) -> bool {
+    fs::read_to_string(manifest)
+        .ok()
+        .and_then(|manifest| manifest.parse::<toml::Value>().ok())
+        .map(|manifest| manifest.get("workspace").is_some())
+        .unwrap_or(false)
+}
+
+/// Where the project and its build output are.
+#[derive(PartialEq, Debug)]
+pub struct Project {
+    root: PathBuf,
+    target_dir: PathBuf,
+}
+
+impl Project {
+    /// Locates the project of `--root`, `--manifest-path`, `CARGO_MANIFEST_DIR` or the current
+    /// directory, in this order.
+    ///
+    /// The workspace root and the target directory are asked to `cargo metadata`, which honors
+    /// `CARGO_TARGET_DIR`, and are found by walking up to the nearest `Cargo.toml` when cargo
+    /// is not available.
+    pub fn locate(proj_root: Option<&Path>, manifest_path: Option<&Path>) -> Self {
+        if let Some(proj_root) = proj_root {
+            let manifest_path = proj_root.join(MANIFEST_FILE);
+            return cargo_metadata(Some(manifest_path.as_path()))
+                .map(|(_, target_dir)| Project {
+                    root: proj_root.to_path_buf(),
+                    target_dir,
+                })
+                .unwrap_or_else(|| Project::without_cargo(proj_root.to_path_buf()));
+        }
+
+        let manifest_path = manifest_path.map(Path::to_path_buf).or_else(|| {
+            env::var_os("CARGO_MANIFEST_DIR")
+                .map(|manifest_dir| PathBuf::from(manifest_dir).join(MANIFEST_FILE))
+        });
+        if let Some((root, target_dir)) = cargo_metadata(manifest_path.as_deref()) {
+            return Project { root, target_dir };
+        }
+
+        let start = manifest_path
+            .as_deref()
+            .and_then(Path::parent)
+            .map(Path::to_path_buf)
+            .or_else(|| env::current_dir().ok());
+        let root = start
+            .and_then(|start| find_project_root(start.as_path()))
+            .unwrap_or_else(|| PathBuf::from("."));
+        Project::without_cargo(root)
+    }
+
+    fn without_cargo(root: PathBuf) -> Self {
+        let target_dir = env::var_os("CARGO_TARGET_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| root.join("target"));
+        Project { root, target_dir }
+    }
+
+    pub fn root(&self) -> &Path {
+        self.root.as_path()
+    }
+
+    pub fn target_dir(&self) -> &Path {
+        self.target_dir.as_path()
+    }
+}
+
+/// The workspace root and the target directory from `cargo metadata`.
+fn cargo_metadata(manifest_path: Option<&Path>) -> Option<(PathBuf, PathBuf)> {
+    let mut command = Command::new("cargo");
+    command.args(["metadata", "--format-version", "1", "--no-deps"]);
+    if let Some(manifest_path) = manifest_path {
+        command.arg("--manifest-path").arg(manifest_path);
+    }
+
+    let output = command.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_cargo_metadata(String::from_utf8_lossy(&output.stdout).as_ref())
+}
+
+pub fn parse_cargo_metadata(metadata: &str) -> Option<(PathBuf, PathBuf)> {
+    let metadata: serde_json::Value = serde_json::from_str(metadata).ok()?;
+    let workspace_root = metadata.get("workspace_root")?.as_str()?;
+    let target_dir = metadata.get("target_directory")?.as_str()?;
+    Some((PathBuf::from(workspace_root), PathBuf::from(target_dir)))
+}
+
+/// Drops the subcommand name which cargo passes to `cargo-andrust` for `cargo andrust`.
+pub fn strip_cargo_subcommand(program: &str, mut args: Vec<String>) -> Vec<String> {
+    let is_cargo_subcommand = Path::new(program)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(|stem| stem == "cargo-andrust")
+        .unwrap_or(false);
+    if is_cargo_subcommand && args.first().map(String::as_str) == Some("andrust") {
+        args.remove(0);
+    }
+    args
+}
+
+/// `(major, minor)` of `cargo --version`.
+pub fn cargo_version() -> Option<(u32, u32)> {
+    let output = Command::new("cargo").arg("--version").output().ok()?;
+    parse_cargo_version(String::from_utf8_lossy(&output.stdout).as_ref())
+}
+
+/// The targets installed by `rustup target list --installed`.
+pub fn rustup_targets() -> Vec<String> {
+    Command::new("rustup")
+        .args(["target", "list", "--installed"])
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(|line| line.trim().to_owned())
+                .filter(|line| !line.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Parses `cargo 1.39.0 (1c6ec66d5 2019-09-30)`.
+pub fn parse_cargo_version(version: &str) -> Option<(u32, u32)> {
+    let mut numbers = version.split_whitespace().nth(1)?.split('.');
+    let major = numbers.next()?.parse().ok()?;
+    let minor = numbers.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// The cargo config of `proj_root`.
+///
+/// An existing `.cargo/config.toml` or `.cargo/config` is kept, and a new config is
+/// `.cargo/config.toml` unless the cargo is older than 1.39, which only reads `.cargo/config`.
+pub fn cargo_config_path(proj_root: &Path, cargo_version: Option<(u32, u32)>) -> PathBuf {
+    let cargo_dir = proj_root.join(".cargo");
+    let config_toml = cargo_dir.join("config.toml");
+    let config = cargo_dir.join("config");
+    if config_toml.is_file() {
+        config_toml
+    } else if config.is_file() {
+        config
+    } else {
+        match cargo_version {
+            Some(version) if version < (1, 39) => config,
+            _ => config_toml,
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use super::project::{
+    cargo_config_path, find_project_root, parse_cargo_metadata, parse_cargo_version,
+    strip_cargo_subcommand, Project,
+};
+
+use std::fs;
+
+#[test]
+fn find_project_root_test() {
+    let root = std::env::temp_dir().join("andrust_project_root_test");
+    let _ = fs::remove_dir_all(&root);
+
+    let crate_src = root.join("crates/app/src");
+    fs::create_dir_all(&crate_src).unwrap();
+    fs::write(
+        root.join("crates/app/Cargo.toml"),
+        "[package]\nname = \"app\"\n",
+    )
+    .unwrap();
+    assert_eq!(
+        find_project_root(crate_src.as_path()),
+        Some(root.join("crates/app"))
+    );
+
+    fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"crates/app\"]\n",
+    )
+    .unwrap();
+    assert_eq!(find_project_root(crate_src.as_path()), Some(root.clone()));
+
+    let _ = fs::remove_dir_all(&root);
+    assert_eq!(find_project_root(root.as_path()), None);
+}
+
+#[test]
+fn cargo_config_path_test() {
+    assert_eq!(
+        parse_cargo_version("cargo 1.39.0 (1c6ec66d5 2019-09-30)"),
+        Some((1, 39))
+    );
+    assert_eq!(parse_cargo_version(""), None);
+
+    let root = std::env::temp_dir().join("andrust_cargo_config_path_test");
+    let _ = fs::remove_dir_all(&root);
+    let cargo_dir = root.join(".cargo");
+    fs::create_dir_all(&cargo_dir).unwrap();
+
+    let path = cargo_config_path(root.as_path(), Some((1, 70)));
+    assert_eq!(path, cargo_dir.join("config.toml"));
+    let path = cargo_config_path(root.as_path(), Some((1, 38)));
+    assert_eq!(path, cargo_dir.join("config"));
+
+    // An existing config is updated instead of adding another one.
+    fs::write(cargo_dir.join("config"), "").unwrap();
+    let path = cargo_config_path(root.as_path(), Some((1, 70)));
+    assert_eq!(path, cargo_dir.join("config"));
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn cargo_subcommand_test() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    assert_eq!(
+        strip_cargo_subcommand(
+            "/home/me/.cargo/bin/cargo-andrust",
+            args(&["andrust", "doctor"])
+        ),
+        args(&["doctor"])
+    );
+    assert_eq!(
+        strip_cargo_subcommand("cargo-andrust.exe", args(&["andrust"])),
+        args(&[])
+    );
+    assert_eq!(
+        strip_cargo_subcommand("andrust", args(&["andrust"])),
+        args(&["andrust"])
+    );
+
+    let metadata = r#"{"packages":[],"workspace_root":"/work/app","target_directory":"/tmp/target","version":1}"#;
+    assert_eq!(
+        parse_cargo_metadata(metadata),
+        Some(("/work/app".into(), "/tmp/target".into()))
+    );
+    assert_eq!(parse_cargo_metadata("error"), None);
+}
+
+#[test]
+fn locate_project_test() {
+    let root = std::env::temp_dir().join("andrust_locate_project_test");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("src")).unwrap();
+    fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"located\"\nversion = \"0.1.0\"\n\n[workspace]\n",
+    )
+    .unwrap();
+    fs::write(root.join("src/lib.rs"), "").unwrap();
+
+    let project = Project::locate(Some(root.as_path()), None);
+    assert_eq!(project.root(), root.as_path());
+
+    let manifest_path = root.join("Cargo.toml");
+    let project = Project::locate(None, Some(manifest_path.as_path()));
+    assert_eq!(
+        project.root().canonicalize().unwrap(),
+        root.canonicalize().unwrap()
+    );
+    let _ = fs::remove_dir_all(&root);
+}
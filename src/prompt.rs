@@ -0,0 +1,231 @@
+use std::{
+    env, fmt,
+    io::{self, stdin, stdout, IsTerminal, Write},
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    style::Print,
+    terminal, QueueableCommand,
+};
+
+/// A question andrust asks on stdin.
+///
+/// In non-interactive mode a prompt is not asked, it fails with its own exit code
+/// and tells how to answer it without stdin.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Prompt {
+    Wizard,
+    UnverifiedDownload,
+}
+
+impl Prompt {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Prompt::Wizard => 4,
+            Prompt::UnverifiedDownload => 5,
+        }
+    }
+
+    /// The options or environment variables which answer the prompt.
+    pub fn fix(self) -> &'static str {
+        match self {
+            Prompt::Wizard => "remove --wizard, and pass --ndk, --targets and --api-level instead",
+            Prompt::UnverifiedDownload => {
+                "add size and sha1 of the release to ~/.andrust/download.json, or pass --ndk <path>"
+            }
+        }
+    }
+}
+
+impl fmt::Display for Prompt {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let question = match self {
+            Prompt::Wizard => "the NDK, targets and API level in the wizard",
+            Prompt::UnverifiedDownload => "to download an NDK which the catalog has no checksum of",
+        };
+        write!(
+            formatter,
+            "andrust would ask {}, but it runs non-interactively ; {}",
+            question,
+            self.fix()
+        )
+    }
+}
+
+/// Whether andrust has to run without prompts, when stdin is not a terminal or `CI` is set.
+pub fn detect_non_interactive() -> bool {
+    is_ci(env::var("CI").ok().as_deref()) || !stdin().is_terminal()
+}
+
+/// `CI` is set by most CI services, to `true` or `1` and sometimes to a service name.
+pub fn is_ci(ci: Option<&str>) -> bool {
+    match ci.map(str::trim) {
+        None | Some("") => false,
+        Some(ci) => !["0", "false", "no"].contains(&ci.to_lowercase().as_str()),
+    }
+}
+
+/// Prints `question` and reads a line without the line break from stdin.
+///
+/// The end of stdin is an error instead of an empty answer.
+pub fn ask(question: &str) -> io::Result<String> {
+    print!("{}", question);
+    let _ = stdout().flush();
+
+    let mut answer = String::new();
+    if stdin().read_line(&mut answer)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "stdin is closed",
+        ));
+    }
+    Ok(answer.trim_end_matches(&['\r', '\n'][..]).to_owned())
+}
+
+/// Asks `question`, and returns whether the answer is yes.
+pub fn ask_yes_no(question: &str) -> bool {
+    ask(question)
+        .map(|answer| matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+        .unwrap_or(false)
+}
+
+/// What a key did to a `ChoiceList`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ListOutcome {
+    Pending,
+    Chosen,
+    Canceled,
+}
+
+/// A list in the terminal, chosen with the arrow keys and `Enter`.
+///
+/// With checkboxes, `Space` toggles the item under the cursor and `a` toggles every item.
+pub struct ChoiceList {
+    items: Vec<String>,
+    cursor: usize,
+    checked: Option<Vec<bool>>,
+}
+
+impl ChoiceList {
+    pub fn single(items: Vec<String>, cursor: usize) -> Self {
+        ChoiceList {
+            cursor: cursor.min(items.len().saturating_sub(1)),
+            items,
+            checked: None,
+        }
+    }
+
+    pub fn checkboxes(items: Vec<String>, checked: Vec<bool>) -> Self {
+        let mut checked = checked;
+        checked.resize(items.len(), false);
+        ChoiceList {
+            items,
+            cursor: 0,
+            checked: Some(checked),
+        }
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn checked(&self) -> &[bool] {
+        self.checked.as_deref().unwrap_or(&[])
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> ListOutcome {
+        let count = self.items.len();
+        match (key.code, self.checked.as_mut()) {
+            (KeyCode::Char('c'), _) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return ListOutcome::Canceled
+            }
+            (KeyCode::Esc, _) | (KeyCode::Char('q'), _) => return ListOutcome::Canceled,
+            (KeyCode::Up, _) | (KeyCode::Char('k'), _) => {
+                self.cursor = (self.cursor + count - 1) % count
+            }
+            (KeyCode::Down, _) | (KeyCode::Char('j'), _) | (KeyCode::Tab, _) => {
+                self.cursor = (self.cursor + 1) % count
+            }
+            (KeyCode::Char(' '), Some(checked)) => checked[self.cursor] = !checked[self.cursor],
+            (KeyCode::Char('a'), Some(checked)) => {
+                let all = !checked.iter().all(|&checked| checked);
+                checked.iter_mut().for_each(|checked| *checked = all);
+            }
+            // A checkbox list needs at least one checked item.
+            (KeyCode::Enter, Some(checked)) if !checked.contains(&true) => (),
+            (KeyCode::Enter, _) => return ListOutcome::Chosen,
+            _ => (),
+        }
+        ListOutcome::Pending
+    }
+
+    /// The lines of the items, with the cursor and the checkboxes.
+    pub fn lines(&self) -> Vec<String> {
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let cursor = if index == self.cursor { ">" } else { " " };
+                match self.checked.as_ref() {
+                    Some(checked) if checked[index] => format!("{} [x] {}", cursor, item),
+                    Some(_) => format!("{} [ ] {}", cursor, item),
+                    None => format!("{} {}", cursor, item),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Shows `title` and `list` until an item is chosen, and returns `false` when it is canceled.
+pub fn choose(title: &str, list: &mut ChoiceList) -> io::Result<bool> {
+    if list.items.is_empty() {
+        return Ok(false);
+    }
+
+    let hint = if list.checked.is_some() {
+        "up/down to move, space to check, a to check all, enter to confirm, esc to cancel"
+    } else {
+        "up/down to move, enter to choose, esc to cancel"
+    };
+    println!("{}\n  ({})", title, hint);
+
+    terminal::enable_raw_mode().map_err(terminal_error)?;
+    let mut stdout = stdout();
+    let _ = stdout.queue(cursor::Hide);
+    let result = (|| {
+        let mut drawn = false;
+        loop {
+            if drawn {
+                stdout
+                    .queue(cursor::MoveUp(list.items.len() as u16))
+                    .map_err(terminal_error)?;
+            }
+            for line in list.lines() {
+                stdout
+                    .queue(terminal::Clear(terminal::ClearType::CurrentLine))
+                    .and_then(|stdout| stdout.queue(Print(format!("{}\r\n", line))))
+                    .map_err(terminal_error)?;
+            }
+            stdout.flush()?;
+            drawn = true;
+
+            if let Event::Key(key) = event::read().map_err(terminal_error)? {
+                match list.handle_key(key) {
+                    ListOutcome::Pending => (),
+                    ListOutcome::Chosen => return Ok(true),
+                    ListOutcome::Canceled => return Ok(false),
+                }
+            }
+        }
+    })();
+    let _ = stdout.queue(cursor::Show);
+    let _ = stdout.flush();
+    let _ = terminal::disable_raw_mode();
+    result
+}
+
+fn terminal_error<E: fmt::Display>(err: E) -> io::Error {
+    io::Error::other(err.to_string())
+}
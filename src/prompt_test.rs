@@ -0,0 +1,75 @@
+use super::{
+    command::CommandState,
+    platform::{LinuxConfig, Platform, PlatformError},
+    prompt::{self, ChoiceList, ListOutcome, Prompt},
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[test]
+fn non_interactive_test() {
+    assert!(!prompt::is_ci(None));
+    assert!(!prompt::is_ci(Some("")));
+    assert!(!prompt::is_ci(Some("false")));
+    assert!(!prompt::is_ci(Some("0")));
+    assert!(prompt::is_ci(Some("true")));
+    assert!(prompt::is_ci(Some("1")));
+    assert!(prompt::is_ci(Some("woodpecker")));
+
+    match CommandState::from_args(vec!["--non-interactive", "setup"]) {
+        CommandState::Options(cmd_opts) => {
+            assert!(!cmd_opts.interactive());
+            // The NDK root is not asked, so that the NDK is downloaded instead.
+            let platform = LinuxConfig::new(Some(*cmd_opts));
+            assert!(matches!(platform.ask_verified_ndk_root(), Ok(None)));
+        }
+        _ => panic!("--non-interactive is a global option"),
+    }
+    match CommandState::from_args(Vec::<String>::new()) {
+        CommandState::Options(cmd_opts) => assert!(cmd_opts.interactive()),
+        _ => panic!("no argument is setup"),
+    }
+
+    let err = PlatformError::InteractionRequired(Prompt::UnverifiedDownload);
+    assert_eq!(err.exit_code(), 5);
+    assert!(err.to_string().contains("download.json"));
+    assert_eq!(PlatformError::ToolsetDoesNotExist.exit_code(), 1);
+}
+
+fn press(list: &mut ChoiceList, code: KeyCode) -> ListOutcome {
+    list.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+#[test]
+fn choice_list_test() {
+    let items = || vec!["r25c".to_owned(), "r26b".to_owned(), "download".to_owned()];
+
+    let mut list = ChoiceList::single(items(), 0);
+    assert_eq!(press(&mut list, KeyCode::Up), ListOutcome::Pending);
+    assert_eq!(list.cursor(), 2);
+    press(&mut list, KeyCode::Down);
+    press(&mut list, KeyCode::Char('j'));
+    assert_eq!(list.cursor(), 1);
+    assert_eq!(list.lines()[1], "> r26b");
+    assert_eq!(press(&mut list, KeyCode::Enter), ListOutcome::Chosen);
+    assert_eq!(press(&mut list, KeyCode::Esc), ListOutcome::Canceled);
+    assert_eq!(
+        list.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+        ListOutcome::Canceled
+    );
+
+    let mut list = ChoiceList::checkboxes(items(), vec![true, false]);
+    assert_eq!(list.checked(), &[true, false, false]);
+    press(&mut list, KeyCode::Char(' '));
+    assert_eq!(list.lines()[0], "> [ ] r25c");
+    // Nothing is checked, so enter does not confirm.
+    assert_eq!(press(&mut list, KeyCode::Enter), ListOutcome::Pending);
+    press(&mut list, KeyCode::Char('a'));
+    assert_eq!(list.checked(), &[true, true, true]);
+    press(&mut list, KeyCode::Char('a'));
+    assert_eq!(list.checked(), &[false, false, false]);
+    press(&mut list, KeyCode::Down);
+    press(&mut list, KeyCode::Char(' '));
+    assert_eq!(list.lines()[1], "> [x] r26b");
+    assert_eq!(press(&mut list, KeyCode::Enter), ListOutcome::Chosen);
+}
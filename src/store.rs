@@ -0,0 +1,151 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::unarchiver;
+
+const MANIFEST_FILE: &str = "installed.json";
+const DOWNLOAD_DIR: &str = "downloads";
+
+/// NDKs installed by andrust, one directory per version under `root`.
+///
+/// ```text
+/// <root>/installed.json
+/// <root>/downloads/
+/// <root>/<version>/
+/// ```
+pub struct NdkStore {
+    root: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Manifest {
+    #[serde(default)]
+    installed: Vec<InstalledNdk>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct InstalledNdk {
+    version: String,
+    path: PathBuf,
+    installed_at: u64,
+}
+
+impl NdkStore {
+    pub fn new(root: PathBuf) -> Self {
+        NdkStore { root }
+    }
+
+    pub fn download_dir(&self) -> PathBuf {
+        self.root.join(DOWNLOAD_DIR)
+    }
+
+    pub fn manifest(&self) -> Manifest {
+        fs::read_to_string(self.root.join(MANIFEST_FILE))
+            .ok()
+            .and_then(|json| serde_json::from_str(json.as_str()).ok())
+            .unwrap_or_default()
+    }
+
+    fn save_manifest(&self, manifest: &Manifest) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(manifest)?;
+        fs::write(self.root.join(MANIFEST_FILE), json)
+    }
+
+    /// Installed NDKs whose directories still exist, in installation order.
+    pub fn installed(&self) -> Vec<InstalledNdk> {
+        self.manifest()
+            .installed
+            .into_iter()
+            .filter(|ndk| ndk.path.is_dir())
+            .collect()
+    }
+
+    pub fn path(&self, version: &str) -> Option<PathBuf> {
+        self.installed()
+            .into_iter()
+            .find(|ndk| ndk.version == version)
+            .map(|ndk| ndk.path)
+    }
+
+    /// Extracts `archive` and moves it to `<root>/<version>`.
+    ///
+    /// The archive is extracted into a temporary directory first, so an interrupted
+    /// install never leaves a half extracted NDK at the final path.
+    pub fn install(&self, version: &str, archive: &Path) -> io::Result<PathBuf> {
+        let temp_dir = self.root.join(format!(".tmp-{}", version));
+        if temp_dir.exists() {
+            fs::remove_dir_all(&temp_dir)?;
+        }
+        fs::create_dir_all(&temp_dir)?;
+
+        let archive_file = fs::File::open(archive)?;
+        if let Err(err) = unarchiver::unzip(&archive_file, temp_dir.as_path()) {
+            let _ = fs::remove_dir_all(&temp_dir);
+            return Err(err);
+        }
+
+        // NDK archives have a single top level directory such as `android-ndk-r21b`.
+        let mut entries = fs::read_dir(&temp_dir)?.collect::<io::Result<Vec<_>>>()?;
+        let extracted_root = if entries.len() == 1 && entries[0].path().is_dir() {
+            entries.remove(0).path()
+        } else {
+            temp_dir.clone()
+        };
+
+        let install_path = self.root.join(version);
+        if install_path.exists() {
+            fs::remove_dir_all(&install_path)?;
+        }
+        fs::rename(&extracted_root, &install_path)?;
+        if temp_dir.exists() {
+            let _ = fs::remove_dir_all(&temp_dir);
+        }
+
+        let mut manifest = self.manifest();
+        manifest.installed.retain(|ndk| ndk.version != version);
+        manifest.installed.push(InstalledNdk {
+            version: version.to_owned(),
+            path: install_path.clone(),
+            installed_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0),
+        });
+        self.save_manifest(&manifest)?;
+
+        Ok(install_path)
+    }
+
+    pub fn remove(&self, version: &str) -> io::Result<bool> {
+        let mut manifest = self.manifest();
+        let removed = match manifest
+            .installed
+            .iter()
+            .position(|ndk| ndk.version == version)
+        {
+            Some(index) => manifest.installed.remove(index),
+            None => return Ok(false),
+        };
+
+        if removed.path.exists() {
+            fs::remove_dir_all(&removed.path)?;
+        }
+        self.save_manifest(&manifest)?;
+        Ok(true)
+    }
+}
+
+impl InstalledNdk {
+    pub fn version(&self) -> &str {
+        self.version.as_str()
+    }
+
+    pub fn path(&self) -> &Path {
+        self.path.as_path()
+    }
+}
@@ -0,0 +1,44 @@
+use super::store::NdkStore;
+
+use std::{fs, io::Write, path::Path};
+
+fn write_ndk_archive(archive_path: &Path, top_dir: &str) {
+    use zip::{write::FileOptions, ZipWriter};
+
+    let mut zip = ZipWriter::new(fs::File::create(archive_path).unwrap());
+    zip.add_directory(format!("{}/", top_dir), FileOptions::default())
+        .unwrap();
+    zip.start_file(
+        format!("{}/source.properties", top_dir),
+        FileOptions::default(),
+    )
+    .unwrap();
+    zip.write_all(b"Pkg.Revision = 21.1.6352462\n").unwrap();
+    zip.finish().unwrap();
+}
+
+#[test]
+fn install_and_remove_test() {
+    let root = std::env::temp_dir().join("andrust_store_test");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    let archive_path = root.join("android-ndk-r21b-linux-x86_64.zip");
+    write_ndk_archive(archive_path.as_path(), "android-ndk-r21b");
+
+    let store = NdkStore::new(root.clone());
+    let ndk_root = store.install("r21b", archive_path.as_path()).unwrap();
+
+    assert_eq!(ndk_root, root.join("r21b"));
+    assert!(ndk_root.join("source.properties").exists());
+    assert!(!root.join(".tmp-r21b").exists());
+    assert_eq!(store.path("r21b"), Some(ndk_root.clone()));
+
+    // A reopened store reads the same manifest.
+    let store = NdkStore::new(root.clone());
+    assert_eq!(store.installed().len(), 1);
+
+    assert!(store.remove("r21b").unwrap());
+    assert!(!ndk_root.exists());
+    assert!(store.installed().is_empty());
+    let _ = fs::remove_dir_all(&root);
+}
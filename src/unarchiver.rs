@@ -1,51 +1,104 @@
 use std::fs;
 use std::io;
-use std::sync::Arc;
+use std::path::Path;
 
 use zip::ZipArchive;
 
-#[tokio::main]
-pub async fn unzip(zip_file: &fs::File) -> io::Result<()> {
-    let archive = Arc::new(ZipArchive::new(zip_file).unwrap());
+#[cfg(unix)]
+const S_IFMT: u32 = 0o170000;
+#[cfg(unix)]
+const S_IFLNK: u32 = 0o120000;
+
+pub fn unzip(zip_file: &fs::File, dest: &Path) -> io::Result<()> {
+    let mut archive =
+        ZipArchive::new(zip_file).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
 
-    let mut file_write_results = Vec::new();
     for i in 0..archive.len() {
-        let arc = Arc::clone(&archive);
-        let file_result = extract_zip_file(arc.clone(), i);
-        file_write_results.push(file_result)
+        let mut zip_file = archive
+            .by_index(i)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        extract_zip_file(&mut zip_file, dest)?;
     }
 
-    let _ = futures::future::join_all(file_write_results).await;
-
     Result::Ok(())
 }
 
-async fn extract_zip_file(archive: Arc<ZipArchive<&fs::File>>, index: usize) -> io::Result<u64> {
-    println!("extract_zip_file - i : {}", index);
-    let mut archive = Arc::clone(&archive);
-    let archive = Arc::make_mut(&mut archive);
-    let mut zip_file = archive.by_index(index).unwrap();
-
-    let outpath = zip_file.enclosed_name().unwrap();
-    println!("extract_zip_file - outpath : {}", &outpath.display());
+fn extract_zip_file(zip_file: &mut zip::read::ZipFile, dest: &Path) -> io::Result<()> {
+    let outpath = match zip_file.enclosed_name() {
+        Some(name) => dest.join(name),
+        None => return Ok(()),
+    };
 
     if zip_file.is_dir() || zip_file.name().ends_with('/') {
-        println!("File extracted to \"{}\"", outpath.display());
-        tokio::fs::create_dir_all(&outpath).await.unwrap();
-        Ok(0)
-    } else {
-        println!(
-            "File extracted to \"{}\" ({} bytes)",
-            outpath.display(),
-            zip_file.size()
-        );
-        if let Some(path) = outpath.parent() {
-            if !path.exists() {
-                tokio::fs::create_dir_all(&path).await.unwrap();
+        return fs::create_dir_all(&outpath);
+    }
+    if let Some(path) = outpath.parent() {
+        if !path.exists() {
+            fs::create_dir_all(path)?;
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        use std::io::Read;
+        use std::os::unix::fs::PermissionsExt;
+
+        match zip_file.unix_mode() {
+            Some(mode) if mode & S_IFMT == S_IFLNK => {
+                let mut target = String::new();
+                zip_file.read_to_string(&mut target)?;
+                return create_symlink(target.as_str(), outpath.as_path(), dest);
             }
+            Some(mode) => {
+                let mut outfile = fs::File::create(&outpath)?;
+                io::copy(zip_file, &mut outfile)?;
+                return fs::set_permissions(&outpath, fs::Permissions::from_mode(mode));
+            }
+            None => (),
+        }
+    }
+
+    let mut outfile = fs::File::create(&outpath)?;
+    io::copy(zip_file, &mut outfile)?;
+    Ok(())
+}
+
+/// Links `link` to `target`, which must stay inside `dest` like the entry names do.
+#[cfg(unix)]
+fn create_symlink(target: &str, link: &Path, dest: &Path) -> io::Result<()> {
+    let link_dir = link.parent().unwrap_or(dest);
+    let depth = link_dir
+        .strip_prefix(dest)
+        .map_or(0, |dir| dir.components().count());
+    if !stays_within(Path::new(target), depth) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} links outside of the archive : {}",
+                link.display(),
+                target
+            ),
+        ));
+    }
+
+    if fs::symlink_metadata(link).is_ok() {
+        fs::remove_file(link)?;
+    }
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// Whether a relative `target` of a link `depth` directories below the root stays under it.
+#[cfg(unix)]
+fn stays_within(target: &Path, mut depth: usize) -> bool {
+    use std::path::Component;
+
+    for component in target.components() {
+        match component {
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => (),
+            Component::ParentDir if depth > 0 => depth -= 1,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return false,
         }
-        let mut outfile = fs::File::create(&outpath).unwrap();
-        io::copy(&mut zip_file, &mut outfile).unwrap();
-        Ok(0)
     }
+    true
 }
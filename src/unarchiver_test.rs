@@ -5,6 +5,24 @@ fn unzip_test() {
     use std::fs;
 
     let file_name = std::path::Path::new("zip_test/test.zip");
-    let file = fs::File::open(&file_name).unwrap();
-    let _ = unarchiver::unzip(&file);
+    let file = fs::File::open(file_name).unwrap();
+    let _ = fs::remove_dir_all("zip_test/test");
+    unarchiver::unzip(&file, std::path::Path::new("zip_test")).unwrap();
+    assert_eq!(
+        fs::read_to_string("zip_test/test/hello.txt").unwrap(),
+        "hello andrust\n"
+    );
+    assert!(std::path::Path::new("zip_test/test/bin/tool").is_file());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tool = fs::metadata("zip_test/test/bin/tool").unwrap();
+        assert_eq!(tool.permissions().mode() & 0o777, 0o755);
+        // `ld -> tool` is extracted as a link, not as a file with the target as its content.
+        let ld = std::path::Path::new("zip_test/test/bin/ld");
+        assert_eq!(fs::read_link(ld).unwrap(), std::path::Path::new("tool"));
+        assert_eq!(fs::read_to_string(ld).unwrap(), "#!/bin/sh\n");
+    }
 }
@@ -0,0 +1,199 @@
+use std::{
+    collections::BTreeSet,
+    io::{stdout, IsTerminal},
+    path::PathBuf,
+};
+
+use super::{
+    command::{CommandOptions, Wizard},
+    downloader::BuildPlatformConfig,
+    lock::LOCK_FILE,
+    ndk::NdkRevision,
+    platform::{
+        ApiLevels, Platform, PlatformError, TargetSelection, TargetSpec, ToolsetLayout,
+        TARGET_SPECS,
+    },
+    prompt::{self, ChoiceList, Prompt},
+};
+
+/// Whether `setup` runs the wizard : with `--wizard`, or in a terminal on the first run
+/// of a project, which has no `andrust.lock` yet.
+pub fn should_run(cmd_opts: &CommandOptions) -> Result<bool, PlatformError> {
+    match cmd_opts.wizard() {
+        Wizard::Never => Ok(false),
+        Wizard::Always if !cmd_opts.interactive() => {
+            Err(PlatformError::InteractionRequired(Prompt::Wizard))
+        }
+        Wizard::Always => Ok(true),
+        Wizard::Auto => Ok(cmd_opts.interactive()
+            && stdout().is_terminal()
+            && !cmd_opts.dry_run()
+            && !cmd_opts.diff()
+            && !cmd_opts
+                .proj_root()
+                .map(|proj_root| proj_root.join(LOCK_FILE).exists())
+                .unwrap_or(false)),
+    }
+}
+
+/// Asks the NDK, the targets and the API level, and sets them to `cmd_opts`.
+///
+/// `platform` finds and downloads the NDKs. The cargo config is previewed and confirmed
+/// before it is written. It returns `false` when the user cancels the wizard.
+pub fn run<P: Platform>(
+    platform: &P,
+    cmd_opts: &mut CommandOptions,
+) -> Result<bool, PlatformError> {
+    let ndk_root = match choose_ndk(platform, cmd_opts)? {
+        Some(ndk_root) => ndk_root,
+        None => return Ok(false),
+    };
+    let revision = NdkRevision::detect(ndk_root.as_path()).ok_or_else(|| {
+        PlatformError::NotInstalled(format!("{} is not an NDK", ndk_root.display()))
+    })?;
+
+    let specs: Vec<&'static TargetSpec> = TARGET_SPECS
+        .iter()
+        .filter(|spec| spec.is_supported_by(&revision))
+        .collect();
+    let items = specs
+        .iter()
+        .map(|spec| format!("{:<26}{}", spec.triple, spec.abi))
+        .collect();
+    let checked = specs
+        .iter()
+        .map(|spec| {
+            cmd_opts
+                .targets()
+                .map(|selection| selection.triples().contains(&spec.triple))
+                .unwrap_or(true)
+        })
+        .collect();
+    let mut targets = ChoiceList::checkboxes(items, checked);
+    if !prompt::choose("Targets", &mut targets).map_err(wizard_error)? {
+        return Ok(false);
+    }
+    let selected: Vec<&'static TargetSpec> = specs
+        .iter()
+        .zip(targets.checked())
+        .filter(|(_, &checked)| checked)
+        .map(|(&spec, _)| spec)
+        .collect();
+    let selection = TargetSelection::from_names(selected.iter().map(|spec| spec.triple))
+        .map_err(PlatformError::InvalidConfig)?;
+
+    let mut api_levels = ApiLevels::default();
+    if ToolsetLayout::for_revision(&revision) == ToolsetLayout::Standalone {
+        println!(
+            "NDK {} has standalone toolchains with their own API levels",
+            revision
+        );
+    } else {
+        // A level below the minimum of an ABI would be raised to it.
+        let minimum = selected
+            .iter()
+            .map(|spec| spec.min_api_level)
+            .max()
+            .unwrap_or(0);
+        let mut levels = common_api_levels(&selected, |spec| {
+            spec.available_api_levels(ndk_root.as_path())
+        });
+        levels.retain(|&level| level >= minimum);
+        let current = selected
+            .first()
+            .and_then(|&spec| cmd_opts.api_levels().level(spec));
+        let mut items = vec!["the minimum of each ABI".to_owned()];
+        items.extend(levels.iter().map(|level| level.to_string()));
+        let cursor = current
+            .and_then(|current| levels.iter().position(|&level| level == current))
+            .map(|index| index + 1)
+            .unwrap_or(0);
+        let mut choices = ChoiceList::single(items, cursor);
+        if !prompt::choose("API level", &mut choices).map_err(wizard_error)? {
+            return Ok(false);
+        }
+        if choices.cursor() > 0 {
+            api_levels.set_default(levels[choices.cursor() - 1]);
+        }
+    }
+
+    cmd_opts.set_ndk_root(ndk_root);
+    cmd_opts.set_targets(selection);
+    cmd_opts.set_api_levels(api_levels);
+    cmd_opts.set_confirm_write(true);
+    Ok(true)
+}
+
+/// Lists the NDKs which are found with their revisions, and offers to download one
+/// or to enter a path.
+fn choose_ndk<P: Platform>(
+    platform: &P,
+    cmd_opts: &CommandOptions,
+) -> Result<Option<PathBuf>, PlatformError> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    paths.extend(cmd_opts.ndk_root().map(|ndk_root| ndk_root.to_path_buf()));
+    paths.extend(P::search_ndk_root(platform.targets()));
+    paths.extend(platform.configured_sdk_ndk_roots());
+    paths.extend(P::ndk_candidates());
+    let mut candidates: Vec<(NdkRevision, PathBuf)> = Vec::new();
+    for path in paths {
+        let known = candidates.iter().any(|(_, known)| *known == path);
+        if let (false, Some(revision)) = (known, NdkRevision::detect(path.as_path())) {
+            candidates.push((revision, path));
+        }
+    }
+    candidates.sort_by(|(lhs, _), (rhs, _)| rhs.cmp(lhs));
+
+    let download = BuildPlatformConfig::default()
+        .version()
+        .map(|version| format!("Download NDK {}", version))
+        .unwrap_or_else(|| "Download the NDK".to_owned());
+    loop {
+        let mut items: Vec<String> = candidates
+            .iter()
+            .map(|(revision, path)| format!("{:<22}{}", revision.to_string(), path.display()))
+            .collect();
+        items.push(download.clone());
+        items.push("Enter the path of an NDK".to_owned());
+
+        let mut choices = ChoiceList::single(items, 0);
+        if !prompt::choose("NDK", &mut choices).map_err(wizard_error)? {
+            return Ok(None);
+        }
+        let index = choices.cursor();
+        if let Some((_, path)) = candidates.get(index) {
+            return Ok(Some(path.clone()));
+        }
+        if index == candidates.len() {
+            return platform.install_ndk(None).map(Some);
+        }
+
+        let path = PathBuf::from(prompt::ask("NDK root path: ").map_err(wizard_error)?.trim());
+        match NdkRevision::detect(path.as_path()) {
+            Some(_) => return Ok(Some(path)),
+            None => println!("{} has no source.properties of an NDK", path.display()),
+        }
+    }
+}
+
+/// The API levels which every one of `specs` has.
+pub fn common_api_levels<F>(specs: &[&'static TargetSpec], available: F) -> Vec<u32>
+where
+    F: Fn(&TargetSpec) -> BTreeSet<u32>,
+{
+    let mut specs = specs.iter();
+    let first = match specs.next() {
+        Some(spec) => available(spec),
+        None => return Vec::new(),
+    };
+    specs
+        .fold(first, |common, spec| {
+            common.intersection(&available(spec)).copied().collect()
+        })
+        .into_iter()
+        .collect()
+}
+
+fn wizard_error(err: std::io::Error) -> PlatformError {
+    PlatformError::TerminalFailed(err.to_string())
+}
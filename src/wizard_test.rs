@@ -0,0 +1,55 @@
+use super::{
+    command::{CommandState, Wizard},
+    platform::{PlatformError, TargetSpec},
+    wizard,
+};
+
+use std::collections::BTreeSet;
+
+#[test]
+fn wizard_options_test() {
+    let wizard_of = |args: &[&str]| match CommandState::from_args(args.iter().copied()) {
+        CommandState::Options(cmd_opts) => cmd_opts.wizard(),
+        _ => panic!("invalid options {:?}", args),
+    };
+    assert_eq!(wizard_of(&[]), Wizard::Auto);
+    assert_eq!(wizard_of(&["--targets", "aarch64"]), Wizard::Never);
+    assert_eq!(
+        wizard_of(&["--wizard", "--ndk", "/opt/ndk"]),
+        Wizard::Always
+    );
+    assert_eq!(
+        wizard_of(&["--api-level", "24", "--wizard"]),
+        Wizard::Always
+    );
+
+    let cmd_opts = match CommandState::from_args(vec!["--wizard", "--non-interactive"]) {
+        CommandState::Options(cmd_opts) => cmd_opts,
+        _ => panic!("--wizard is an option of setup"),
+    };
+    match wizard::should_run(&cmd_opts) {
+        Err(err @ PlatformError::InteractionRequired(_)) => assert_eq!(err.exit_code(), 4),
+        _ => panic!("the wizard can not run non-interactively"),
+    }
+    // A broken terminal is a failure, not a prompt of non-interactive mode.
+    let err = PlatformError::TerminalFailed("raw mode is not supported".to_owned());
+    assert_eq!(err.exit_code(), 1);
+}
+
+#[test]
+fn common_api_levels_test() {
+    let specs = vec![
+        TargetSpec::find("arm64-v8a").unwrap(),
+        TargetSpec::find("armeabi-v7a").unwrap(),
+    ];
+    let levels = wizard::common_api_levels(&specs, |spec| {
+        let levels: BTreeSet<u32> = if spec.abi == "arm64-v8a" {
+            (21..=33).collect()
+        } else {
+            (19..=30).collect()
+        };
+        levels
+    });
+    assert_eq!(levels, (21..=30).collect::<Vec<u32>>());
+    assert!(wizard::common_api_levels(&[], |_| BTreeSet::new()).is_empty());
+}